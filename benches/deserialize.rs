@@ -0,0 +1,59 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_derive::Deserialize;
+use std::hint::black_box;
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Search<'a> {
+    q: String,
+    lang: &'a str,
+    page: u32,
+    per_page: u32,
+    min_price: f64,
+    in_stock: bool,
+}
+
+const SHORT: &str =
+    "q=caf%C3%A9+au+lait&lang=fr&page=3&per_page=50&min_price=12.5&in_stock=true";
+
+/// A query string where most pairs are tracking parameters the struct does
+/// not care about.
+fn tracked() -> String {
+    let mut query = String::from(SHORT);
+    for i in 0..50 {
+        query.push_str(&format!(
+            "&utm_param{}=%7B%22campaign%22%3A%22spring+sale%22%2C%22id%22%3A{}%7D",
+            i, i
+        ));
+    }
+    query
+}
+
+fn deserialize(c: &mut Criterion) {
+    let tracked = tracked();
+
+    let mut group = c.benchmark_group("short");
+    group.bench_function("current", |b| {
+        b.iter(|| serde_urlencoded::from_str::<Search>(black_box(SHORT)))
+    });
+    group.bench_function("0.7.1", |b| {
+        b.iter(|| {
+            serde_urlencoded_baseline::from_str::<Search>(black_box(SHORT))
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("tracked");
+    group.bench_function("current", |b| {
+        b.iter(|| serde_urlencoded::from_str::<Search>(black_box(&tracked)))
+    });
+    group.bench_function("0.7.1", |b| {
+        b.iter(|| {
+            serde_urlencoded_baseline::from_str::<Search>(black_box(&tracked))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, deserialize);
+criterion_main!(benches);
@@ -0,0 +1,71 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::hint::black_box;
+
+#[derive(Serialize)]
+struct Search<'a> {
+    q: &'a str,
+    lang: &'a str,
+    page: u32,
+    per_page: u32,
+    min_price: f64,
+    in_stock: bool,
+    sort: char,
+    category: Option<&'a str>,
+}
+
+fn search() -> Search<'static> {
+    Search {
+        q: "café au lait",
+        lang: "fr",
+        page: 3,
+        per_page: 50,
+        min_price: 12.5,
+        in_stock: true,
+        sort: 'd',
+        category: Some("drinks & snacks"),
+    }
+}
+
+fn pairs() -> Vec<(String, String)> {
+    (0..16)
+        .map(|i| (format!("key{}", i), format!("value {}", i)))
+        .collect()
+}
+
+fn serialize(c: &mut Criterion) {
+    let search = search();
+    let pairs = pairs();
+    let map = pairs.iter().cloned().collect::<BTreeMap<_, _>>();
+
+    let mut group = c.benchmark_group("struct");
+    group.bench_function("current", |b| {
+        b.iter(|| serde_urlencoded::to_string(black_box(&search)))
+    });
+    group.bench_function("0.7.1", |b| {
+        b.iter(|| serde_urlencoded_baseline::to_string(black_box(&search)))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("pairs");
+    group.bench_function("current", |b| {
+        b.iter(|| serde_urlencoded::to_string(black_box(&pairs)))
+    });
+    group.bench_function("0.7.1", |b| {
+        b.iter(|| serde_urlencoded_baseline::to_string(black_box(&pairs)))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("map");
+    group.bench_function("current", |b| {
+        b.iter(|| serde_urlencoded::to_string(black_box(&map)))
+    });
+    group.bench_function("0.7.1", |b| {
+        b.iter(|| serde_urlencoded_baseline::to_string(black_box(&map)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, serialize);
+criterion_main!(benches);
@@ -1,17 +1,45 @@
 //! Deserialization support for the `application/x-www-form-urlencoded` format.
 
-use form_urlencoded::parse;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter;
+use core::str;
 use form_urlencoded::Parse as UrlEncodedParse;
-use serde::de::value::MapDeserializer;
+use percent_encoding::percent_decode;
 use serde::de::Error as de_Error;
 use serde::de::{self, IntoDeserializer};
 use serde::forward_to_deserialize_any;
-use std::borrow::Cow;
+use serde::Serialize;
+#[cfg(feature = "std")]
 use std::io::Read;
 
 #[doc(inline)]
 pub use serde::de::value::Error;
 
+/// Deserializes a single unencoded value, such as a path segment captured by
+/// a router.
+///
+/// This accepts the same types as the values of a struct or map.
+///
+/// ```
+/// use serde_urlencoded::de::from_value_str;
+///
+/// assert_eq!(from_value_str::<u32>("42"), Ok(42));
+/// assert_eq!(from_value_str::<Option<bool>>("true"), Ok(Some(true)));
+/// assert!(from_value_str::<u32>("forty-two").is_err());
+/// ```
+pub fn from_value_str<'de, T>(input: &'de str) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    T::deserialize(ValueDeserializer::new(input))
+}
+
 /// Deserializes a `application/x-www-form-urlencoded` value from a `&[u8]`.
 ///
 /// ```
@@ -31,97 +59,1380 @@ pub fn from_bytes<'de, T>(input: &'de [u8]) -> Result<T, Error>
 where
     T: de::Deserialize<'de>,
 {
-    T::deserialize(Deserializer::new(parse(input)))
+    T::deserialize(Deserializer::from_bytes(input))
+}
+
+/// Deserializes a `application/x-www-form-urlencoded` value from a `&str`.
+///
+/// ```
+/// let meal = vec![
+///     ("bread".to_owned(), "baguette".to_owned()),
+///     ("cheese".to_owned(), "comté".to_owned()),
+///     ("meat".to_owned(), "ham".to_owned()),
+///     ("fat".to_owned(), "butter".to_owned()),
+/// ];
+///
+/// assert_eq!(
+///     serde_urlencoded::from_str::<Vec<(String, String)>>(
+///         "bread=baguette&cheese=comt%C3%A9&meat=ham&fat=butter"),
+///     Ok(meal));
+/// ```
+pub fn from_str<'de, T>(input: &'de str) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    from_bytes(input.as_bytes())
+}
+
+/// Deserializes a `application/x-www-form-urlencoded` value from a `&[u8]`
+/// with the given `DeserializeSeed`.
+///
+/// ```
+/// use serde::de::DeserializeSeed;
+/// use std::marker::PhantomData;
+///
+/// let seed = PhantomData::<Vec<(String, u32)>>;
+///
+/// assert_eq!(
+///     serde_urlencoded::de::from_bytes_seed(b"first=23&last=42", seed),
+///     Ok(vec![("first".to_owned(), 23), ("last".to_owned(), 42)]));
+/// ```
+pub fn from_bytes_seed<'de, S>(
+    input: &'de [u8],
+    seed: S,
+) -> Result<S::Value, Error>
+where
+    S: de::DeserializeSeed<'de>,
+{
+    seed.deserialize(Deserializer::from_bytes(input))
+}
+
+/// Deserializes a `application/x-www-form-urlencoded` value from a `&str`
+/// with the given `DeserializeSeed`.
+pub fn from_str_seed<'de, S>(
+    input: &'de str,
+    seed: S,
+) -> Result<S::Value, Error>
+where
+    S: de::DeserializeSeed<'de>,
+{
+    from_bytes_seed(input.as_bytes(), seed)
+}
+
+/// Deserializes a `application/x-www-form-urlencoded` value from a `&[u8]`
+/// onto an existing value, only overwriting the fields whose keys appear in
+/// the input.
+///
+/// The existing value is serialized with [`to_string`](crate::to_string)
+/// and the pairs of the input replace its pairs with the same keys before
+/// the result is deserialized back, so the type must round-trip through
+/// this crate. A missing key keeps the current value of its field, which
+/// means that an `Option` field can be set but not cleared this way, while
+/// fields that are not serialized, such as `#[serde(skip)]` ones, get the
+/// value they would get from [`from_bytes`].
+///
+/// `place` is left untouched if anything fails.
+///
+/// ```
+/// #[derive(serde_derive::Deserialize, serde_derive::Serialize)]
+/// struct Profile {
+///     name: String,
+///     age: u32,
+///     city: Option<String>,
+/// }
+///
+/// let mut profile = Profile {
+///     name: "Anthony".to_owned(),
+///     age: 30,
+///     city: None,
+/// };
+/// serde_urlencoded::from_bytes_into(b"city=Paris&age=31", &mut profile)
+///     .unwrap();
+///
+/// assert_eq!(profile.name, "Anthony");
+/// assert_eq!(profile.age, 31);
+/// assert_eq!(profile.city.as_deref(), Some("Paris"));
+/// ```
+pub fn from_bytes_into<T>(input: &[u8], place: &mut T) -> Result<(), Error>
+where
+    T: Serialize + de::DeserializeOwned,
+{
+    let existing = crate::ser::to_string(&*place).map_err(|err| {
+        de::Error::custom(format_args!(
+            "could not serialize existing value: {}",
+            err
+        ))
+    })?;
+    let updates = form_urlencoded::parse(input).collect::<Vec<_>>();
+    let updated_keys = updates
+        .iter()
+        .map(|(key, _)| &**key)
+        .collect::<BTreeSet<_>>();
+    let pairs = form_urlencoded::parse(existing.as_bytes())
+        .filter(|(key, _)| !updated_keys.contains(&**key))
+        .chain(updates.iter().cloned());
+    *place = T::deserialize(Deserializer::from_pairs(pairs))?;
+    Ok(())
+}
+
+/// Deserializes a `application/x-www-form-urlencoded` value from a `&str`
+/// onto an existing value, only overwriting the fields whose keys appear in
+/// the input.
+///
+/// See [`from_bytes_into`] for details.
+pub fn from_str_into<T>(input: &str, place: &mut T) -> Result<(), Error>
+where
+    T: Serialize + de::DeserializeOwned,
+{
+    from_bytes_into(input.as_bytes(), place)
+}
+
+/// Deserializes the query component of a URL, ignoring a leading `?` and
+/// anything starting at the first `#`.
+///
+/// Like [`from_str`], this uses [`Decoding::Form`], so `+` is decoded as a
+/// space. Use a [`Deserializer`] with [`Decoding::Query`] to keep it.
+///
+/// ```
+/// let meal = vec![("bread", "baguette"), ("cheese", "comté")];
+///
+/// assert_eq!(
+///     serde_urlencoded::from_url_query_str::<Vec<(&str, String)>>(
+///         "?bread=baguette&cheese=comt%C3%A9#menu"),
+///     Ok(meal.into_iter().map(|(k, v)| (k, v.to_owned())).collect()));
+/// ```
+pub fn from_url_query_str<'de, T>(input: &'de str) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    from_url_query_bytes(input.as_bytes())
+}
+
+/// Deserializes the query component of a URL from a `&[u8]`, ignoring a
+/// leading `?` and anything starting at the first `#`.
+///
+/// This uses [`Decoding::Form`], see [`from_url_query_str`].
+pub fn from_url_query_bytes<'de, T>(input: &'de [u8]) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    let input = input.strip_prefix(b"?").unwrap_or(input);
+    let end = input.iter().position(|&b| b == b'#').unwrap_or(input.len());
+    from_bytes(&input[..end])
+}
+
+/// Deserializes the query of a `Url`, or an empty query if it has none.
+///
+/// ```
+/// let url = url::Url::parse("https://example.com/?page=2&q=rust").unwrap();
+///
+/// assert_eq!(
+///     serde_urlencoded::from_url::<Vec<(&str, &str)>>(&url),
+///     Ok(vec![("page", "2"), ("q", "rust")]));
+/// ```
+#[cfg(feature = "url")]
+pub fn from_url<'de, T>(url: &'de url::Url) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    from_str(url.query().unwrap_or(""))
+}
+
+/// Deserializes the query of an `http::Uri`, or an empty query if it has none.
+///
+/// ```
+/// let uri: http::Uri = "/search?page=2&q=rust".parse().unwrap();
+///
+/// assert_eq!(
+///     serde_urlencoded::from_uri::<Vec<(&str, &str)>>(&uri),
+///     Ok(vec![("page", "2"), ("q", "rust")]));
+/// ```
+#[cfg(feature = "http")]
+pub fn from_uri<'de, T>(uri: &'de http::Uri) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    from_str(uri.query().unwrap_or(""))
+}
+
+/// Deserializes the body of an `http::Request`.
+///
+/// Fails if the request has no `Content-Type: application/x-www-form-urlencoded`
+/// header, or if that header has a `charset` parameter other than `UTF-8`.
+///
+/// ```
+/// let request = http::Request::post("/login")
+///     .header("content-type", "application/x-www-form-urlencoded; charset=UTF-8")
+///     .body("user=nox&remember=true")
+///     .unwrap();
+///
+/// assert_eq!(
+///     serde_urlencoded::from_request::<Vec<(&str, &str)>, _>(&request),
+///     Ok(vec![("user", "nox"), ("remember", "true")]));
+/// ```
+#[cfg(feature = "http")]
+pub fn from_request<'de, T, B>(
+    request: &'de http::Request<B>,
+) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+    B: AsRef<[u8]>,
+{
+    let content_type = request.headers().get(http::header::CONTENT_TYPE);
+    if !content_type.is_some_and(is_form_content_type) {
+        return Err(Error::custom(
+            "expected Content-Type: application/x-www-form-urlencoded",
+        ));
+    }
+    from_bytes(request.body().as_ref())
+}
+
+#[cfg(feature = "http")]
+fn is_form_content_type(value: &http::HeaderValue) -> bool {
+    let value = match value.to_str() {
+        Ok(value) => value,
+        Err(_) => return false,
+    };
+    let mut params = value.split(';');
+    let essence = params.next().unwrap_or("").trim();
+    if !essence.eq_ignore_ascii_case("application/x-www-form-urlencoded") {
+        return false;
+    }
+    params.all(|param| {
+        let mut param = param.splitn(2, '=');
+        let name = param.next().unwrap_or("").trim();
+        let value = param.next().unwrap_or("").trim().trim_matches('"');
+        !name.eq_ignore_ascii_case("charset")
+            || value.eq_ignore_ascii_case("utf-8")
+    })
+}
+
+/// Convenience function that reads all bytes from `reader` and deserializes
+/// them with `from_bytes`.
+#[cfg(feature = "std")]
+pub fn from_reader<T, R>(mut reader: R) -> Result<T, Error>
+where
+    T: de::DeserializeOwned,
+    R: Read,
+{
+    let mut buf = alloc::vec![];
+    reader.read_to_end(&mut buf).map_err(|e| {
+        de::Error::custom(format_args!("could not read input: {}", e))
+    })?;
+    from_bytes(&buf)
+}
+
+/// Convenience function that reads all bytes from `reader` and deserializes
+/// them with `from_bytes_seed`.
+#[cfg(feature = "std")]
+pub fn from_reader_seed<S, V, R>(mut reader: R, seed: S) -> Result<V, Error>
+where
+    S: for<'de> de::DeserializeSeed<'de, Value = V>,
+    R: Read,
+{
+    let mut buf = alloc::vec![];
+    reader.read_to_end(&mut buf).map_err(|e| {
+        de::Error::custom(format_args!("could not read input: {}", e))
+    })?;
+    from_bytes_seed(&buf, seed)
+}
+
+/// Deserializes an owned value from a `bytes::Bytes` buffer.
+///
+/// While deserializing, unescaped keys and values that end up in a
+/// [`ByteStr`] are sliced out of `input` instead of being copied.
+///
+/// `input` is made known to `ByteStr` through a thread-local set for the
+/// duration of this call only, so sharing only happens for the strings
+/// deserialized by this call, on the calling thread. A `ByteStr` only ever
+/// shares a string found within `input` itself, and copies any other.
+///
+/// ```
+/// use serde_urlencoded::de::ByteStr;
+///
+/// let input = bytes::Bytes::from_static(b"user=nox&city=S%C3%A8te");
+/// let pairs: Vec<(ByteStr, ByteStr)> =
+///     serde_urlencoded::from_shared_bytes(input).unwrap();
+///
+/// assert_eq!(&*pairs[1].1, "Sète");
+/// ```
+#[cfg(feature = "bytes")]
+pub fn from_shared_bytes<T>(input: bytes::Bytes) -> Result<T, Error>
+where
+    T: de::DeserializeOwned,
+{
+    let _source = SharedSource::enter(input.clone());
+    from_bytes(&input)
+}
+
+/// A UTF-8 string backed by a `bytes::Bytes` buffer.
+///
+/// Only when deserialized within a call to [`from_shared_bytes`], on the
+/// same thread, do unescaped strings share the input buffer. Any other
+/// deserialization, including with [`from_bytes`] or another format,
+/// copies the string, so that it behaves like a cheaply clonable `String`.
+#[cfg(feature = "bytes")]
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteStr(bytes::Bytes);
+
+#[cfg(feature = "bytes")]
+impl ByteStr {
+    /// Returns the string as a `&str`.
+    pub fn as_str(&self) -> &str {
+        self
+    }
+
+    /// Returns the underlying bytes.
+    pub fn into_bytes(self) -> bytes::Bytes {
+        self.0
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl core::ops::Deref for ByteStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        // The buffer is only ever built from UTF-8 strings.
+        core::str::from_utf8(&self.0).unwrap_or_default()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl fmt::Debug for ByteStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl fmt::Display for ByteStr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl From<String> for ByteStr {
+    fn from(value: String) -> Self {
+        ByteStr(value.into())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl From<&'static str> for ByteStr {
+    fn from(value: &'static str) -> Self {
+        ByteStr(value.into())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl serde::Serialize for ByteStr {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<'de> de::Deserialize<'de> for ByteStr {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct ByteStrVisitor;
+
+        impl<'de> de::Visitor<'de> for ByteStrVisitor {
+            type Value = ByteStr;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: de::Error>(
+                self,
+                value: &str,
+            ) -> Result<ByteStr, E> {
+                Ok(ByteStr(bytes::Bytes::copy_from_slice(value.as_bytes())))
+            }
+
+            fn visit_borrowed_str<E: de::Error>(
+                self,
+                value: &'de str,
+            ) -> Result<ByteStr, E> {
+                match SharedSource::slice(value) {
+                    Some(shared) => Ok(ByteStr(shared)),
+                    None => self.visit_str(value),
+                }
+            }
+
+            fn visit_string<E: de::Error>(
+                self,
+                value: String,
+            ) -> Result<ByteStr, E> {
+                Ok(value.into())
+            }
+        }
+
+        deserializer.deserialize_str(ByteStrVisitor)
+    }
+}
+
+/// The buffer `from_shared_bytes` is currently deserializing from on this
+/// thread, restored to the previous one when dropped.
+///
+/// `Deserialize` implementations only ever see the deserializer through
+/// its trait, so this is the only way for `ByteStr` to reach the buffer.
+#[cfg(feature = "bytes")]
+struct SharedSource {
+    previous: Option<bytes::Bytes>,
+}
+
+#[cfg(feature = "bytes")]
+std::thread_local! {
+    static SHARED_SOURCE: std::cell::RefCell<Option<bytes::Bytes>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+#[cfg(feature = "bytes")]
+impl SharedSource {
+    fn enter(source: bytes::Bytes) -> Self {
+        let previous = SHARED_SOURCE.with(|cell| cell.replace(Some(source)));
+        SharedSource { previous }
+    }
+
+    fn slice(value: &str) -> Option<bytes::Bytes> {
+        SHARED_SOURCE.with(|cell| {
+            let cell = cell.borrow();
+            let source = cell.as_ref()?;
+            let start = source.as_ptr() as usize;
+            let value_start = value.as_ptr() as usize;
+            let within = value_start >= start
+                && value_start + value.len() <= start + source.len();
+            if within {
+                Some(source.slice_ref(value.as_bytes()))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Drop for SharedSource {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        SHARED_SOURCE.with(|cell| *cell.borrow_mut() = previous);
+    }
+}
+
+/// A deserializer for the `application/x-www-form-urlencoded` format.
+///
+/// * Supported top-level outputs are structs, maps and sequences of pairs,
+///   with or without a given length.
+///
+/// * Main `deserialize` methods defers to `deserialize_map`.
+///
+/// * Everything else but `deserialize_seq` and `deserialize_seq_fixed_size`
+///   defers to `deserialize`.
+///
+/// Deserializing through `&mut Deserializer` leaves the deserializer usable
+/// afterwards, so that [`Deserializer::end`] can check that the whole input
+/// was consumed.
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde_urlencoded::Deserializer;
+///
+/// let mut deserializer = Deserializer::from_str("first=23&last=42");
+/// let pair = <[(&str, u32); 1]>::deserialize(&mut deserializer).unwrap();
+///
+/// assert_eq!(pair, [("first", 23)]);
+/// assert!(deserializer.end().is_err());
+/// ```
+pub struct Deserializer<'de> {
+    pairs: PairAccess<'de>,
+}
+
+impl<'de> Deserializer<'de> {
+    /// Returns a new `Deserializer`.
+    pub fn new(parser: UrlEncodedParse<'de>) -> Self {
+        Deserializer {
+            pairs: PairAccess::new(PartIterator::Parse(parser)),
+        }
+    }
+
+    /// Returns a new `Deserializer` reading from already decoded pairs.
+    ///
+    /// Keys and values are deserialized just like decoded ones, so this
+    /// accepts the same types as [`from_str`].
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_urlencoded::Deserializer;
+    ///
+    /// #[derive(serde_derive::Deserialize)]
+    /// struct Args {
+    ///     name: String,
+    ///     count: u32,
+    /// }
+    ///
+    /// let args = ["name=ferris", "count=3"]
+    ///     .iter()
+    ///     .filter_map(|arg| arg.split_once('='));
+    /// let args = Args::deserialize(Deserializer::from_pairs(args)).unwrap();
+    ///
+    /// assert_eq!(args.name, "ferris");
+    /// assert_eq!(args.count, 3);
+    /// ```
+    ///
+    /// [`from_str`]: Deserializer::from_str
+    pub fn from_pairs<I, K, V>(pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        I::IntoIter: 'de,
+        K: Into<Cow<'de, str>>,
+        V: Into<Cow<'de, str>>,
+    {
+        let pairs = pairs.into_iter().map(|(k, v)| (k.into(), v.into()));
+        Deserializer {
+            pairs: PairAccess::new(PartIterator::Pairs(Box::new(pairs))),
+        }
+    }
+
+    /// Returns a new `Deserializer` reading from a `&[u8]`.
+    pub fn from_bytes(input: &'de [u8]) -> Self {
+        Deserializer {
+            pairs: PairAccess::new(PartIterator::Raw {
+                input,
+                offset: 0,
+                decoding: Decoding::Form,
+            }),
+        }
+    }
+
+    /// Returns a new `Deserializer` reading from a `&str`.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'de str) -> Self {
+        Deserializer::from_bytes(input.as_bytes())
+    }
+
+    /// Checks that all the pairs of the input have been consumed.
+    pub fn end(self) -> Result<(), Error> {
+        self.pairs.end()
+    }
+
+    /// Makes the deserializer record the keys whose values get ignored,
+    /// such as unknown fields of a struct, instead of silently dropping them.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_urlencoded::Deserializer;
+    ///
+    /// #[derive(serde_derive::Deserialize)]
+    /// struct Search {
+    ///     query: String,
+    /// }
+    ///
+    /// let mut deserializer =
+    ///     Deserializer::from_str("query=rust&pgae=2").record_unknown_keys();
+    /// let search = Search::deserialize(&mut deserializer).unwrap();
+    /// let unknown = deserializer.unknown_keys();
+    ///
+    /// assert_eq!(search.query, "rust");
+    /// assert_eq!(unknown.len(), 1);
+    /// assert_eq!(unknown[0].key(), "pgae");
+    /// assert_eq!(unknown[0].offset(), Some(11));
+    /// ```
+    pub fn record_unknown_keys(mut self) -> Self {
+        self.pairs.unknown_keys.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Returns the keys whose values were ignored so far, in input order.
+    ///
+    /// Always empty unless [`record_unknown_keys`] was called.
+    ///
+    /// [`record_unknown_keys`]: Deserializer::record_unknown_keys
+    pub fn unknown_keys(&self) -> &[UnknownKey<'de>] {
+        self.pairs.unknown_keys.as_deref().unwrap_or(&[])
+    }
+
+    /// Sets how keys are matched against the field names of a struct, and
+    /// values against the names of unit variants.
+    ///
+    /// The field and variant names are only known when deserializing a
+    /// struct or an enum, so keys collected through `#[serde(flatten)]` or
+    /// into a map are left as is.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_urlencoded::de::{Deserializer, KeyMatching};
+    ///
+    /// #[derive(serde_derive::Deserialize)]
+    /// enum Sort {
+    ///     Asc,
+    ///     Desc,
+    /// }
+    ///
+    /// #[derive(serde_derive::Deserialize)]
+    /// struct Page {
+    ///     page_size: u32,
+    ///     sort: Sort,
+    /// }
+    ///
+    /// let deserializer = Deserializer::from_str("pageSize=10&SORT=desc")
+    ///     .key_matching(KeyMatching::ConventionInsensitive);
+    /// let page = Page::deserialize(deserializer).unwrap();
+    ///
+    /// assert_eq!(page.page_size, 10);
+    /// assert!(matches!(page.sort, Sort::Desc));
+    /// ```
+    pub fn key_matching(mut self, matching: KeyMatching) -> Self {
+        self.pairs.matching = matching;
+        self
+    }
+
+    /// Makes the deserializer only consider the pairs whose decoded key
+    /// starts with `prefix`, and strip it from their keys.
+    ///
+    /// Other pairs are skipped without being decoded, and are not taken
+    /// into account by [`end`]. Recorded unknown keys keep their prefix.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_urlencoded::Deserializer;
+    ///
+    /// #[derive(serde_derive::Deserialize)]
+    /// struct Page {
+    ///     size: u32,
+    /// }
+    ///
+    /// let input = "filter_name=rust&page.size=20";
+    /// let deserializer = Deserializer::from_str(input).key_prefix("page.");
+    /// let page = Page::deserialize(deserializer).unwrap();
+    ///
+    /// assert_eq!(page.size, 20);
+    /// ```
+    ///
+    /// [`end`]: Deserializer::end
+    pub fn key_prefix<P>(mut self, prefix: P) -> Self
+    where
+        P: Into<Cow<'de, str>>,
+    {
+        self.pairs.prefix = prefix.into();
+        self
+    }
+
+    /// Sets how the input is decoded.
+    ///
+    /// This has no effect on a deserializer built from an already parsed
+    /// `form_urlencoded::Parse` or from decoded pairs.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_urlencoded::de::{Decoding, Deserializer};
+    ///
+    /// let deserializer = Deserializer::from_str("tz=UTC+1&q=a%20b")
+    ///     .decoding(Decoding::Query);
+    /// let pairs = Vec::<(String, String)>::deserialize(deserializer).unwrap();
+    ///
+    /// assert_eq!(pairs[0].1, "UTC+1");
+    /// assert_eq!(pairs[1].1, "a b");
+    /// ```
+    pub fn decoding(mut self, decoding: Decoding) -> Self {
+        if let PartIterator::Raw {
+            decoding: ref mut current,
+            ..
+        } = self.pairs.iter
+        {
+            *current = decoding;
+        }
+        self
+    }
+}
+
+/// How keys and values are decoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Decoding {
+    /// The `application/x-www-form-urlencoded` decoding of HTML forms, which
+    /// turns `+` into a space.
+    #[default]
+    Form,
+    /// The query string decoding of RFC 3986, which only decodes percent
+    /// escapes and keeps `+` as is.
+    Query,
+}
+
+/// How keys and unit variants are matched against the expected names.
+///
+/// An exact match always wins. Otherwise, an input that matches more than
+/// one name is rejected as ambiguous.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyMatching {
+    /// Names must match exactly.
+    #[default]
+    Exact,
+    /// Names are compared ignoring ASCII case, so `SORT` matches `sort`.
+    AsciiCaseInsensitive,
+    /// Names are compared ignoring ASCII case, underscores and dashes, so
+    /// `pageSize`, `PageSize` and `page-size` all match `page_size`.
+    ConventionInsensitive,
+}
+
+impl KeyMatching {
+    fn matches(self, input: &str, name: &str) -> bool {
+        fn significant(s: &str) -> impl Iterator<Item = u8> + '_ {
+            s.bytes()
+                .filter(|&b| b != b'_' && b != b'-')
+                .map(|b| b.to_ascii_lowercase())
+        }
+
+        match self {
+            KeyMatching::Exact => input == name,
+            KeyMatching::AsciiCaseInsensitive => {
+                input.eq_ignore_ascii_case(name)
+            }
+            KeyMatching::ConventionInsensitive => {
+                significant(input).eq(significant(name))
+            }
+        }
+    }
+
+    /// Replaces `input` with the one name it matches, if any.
+    ///
+    /// Inputs with nothing to decode in them are compared as they are.
+    fn resolve<'de>(
+        self,
+        input: Text<'de>,
+        names: &'static [&'static str],
+    ) -> Result<Text<'de>, Error> {
+        match input.as_plain() {
+            Some(plain) => {
+                let name = self.find(plain, names)?;
+                Ok(name.map_or(input, |name| Text::Decoded(name.into())))
+            }
+            None => {
+                let input = input.decode();
+                let name = self.find(&input, names)?;
+                Ok(Text::Decoded(name.map_or(input, Cow::Borrowed)))
+            }
+        }
+    }
+
+    /// Returns the one name `input` matches, if any.
+    fn find(
+        self,
+        input: &str,
+        names: &'static [&'static str],
+    ) -> Result<Option<&'static str>, Error> {
+        if let Some(name) = names.iter().find(|&&name| name == input) {
+            return Ok(Some(name));
+        }
+        if self == KeyMatching::Exact {
+            return Ok(None);
+        }
+        let mut candidates =
+            names.iter().filter(|name| self.matches(input, name));
+        match (candidates.next(), candidates.next()) {
+            (Some(name), None) => Ok(Some(name)),
+            (Some(first), Some(second)) => {
+                Err(de::Error::custom(format_args!(
+                    "`{}` is ambiguous between `{}` and `{}`",
+                    input, first, second
+                )))
+            }
+            (None, _) => Ok(None),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_map(&mut self.pairs)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_seq(&mut self.pairs)
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.pairs.fields = Some(fields);
+        let result = visitor.visit_map(&mut self.pairs);
+        self.pairs.fields = None;
+        result
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.pairs.check_empty()?;
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        bool
+        u8
+        u16
+        u32
+        u64
+        i8
+        i16
+        i32
+        i64
+        f32
+        f64
+        char
+        str
+        string
+        option
+        bytes
+        byte_buf
+        unit_struct
+        newtype_struct
+        tuple_struct
+        identifier
+        enum
+        ignored_any
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        (&mut self).deserialize_any(visitor)
+    }
+
+    fn deserialize_map<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        (&mut self).deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        (&mut self).deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple<V>(
+        mut self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        (&mut self).deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_struct<V>(
+        mut self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        (&mut self).deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_unit<V>(
+        mut self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        (&mut self).deserialize_unit(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool
+        u8
+        u16
+        u32
+        u64
+        i8
+        i16
+        i32
+        i64
+        f32
+        f64
+        char
+        str
+        string
+        option
+        bytes
+        byte_buf
+        unit_struct
+        newtype_struct
+        tuple_struct
+        identifier
+        enum
+        ignored_any
+    }
 }
 
-/// Deserializes a `application/x-www-form-urlencoded` value from a `&str`.
+/// Pairs left over by the other fields of a struct.
+///
+/// Used with `#[serde(flatten)]`, it collects the pairs that no other field
+/// of the struct claimed, in input order and keeping duplicates. It
+/// serializes back to the same pairs.
 ///
 /// ```
-/// let meal = vec![
-///     ("bread".to_owned(), "baguette".to_owned()),
-///     ("cheese".to_owned(), "comté".to_owned()),
-///     ("meat".to_owned(), "ham".to_owned()),
-///     ("fat".to_owned(), "butter".to_owned()),
-/// ];
+/// use serde_urlencoded::de::Rest;
+///
+/// #[derive(serde_derive::Deserialize)]
+/// struct Search {
+///     query: String,
+///     #[serde(flatten)]
+///     rest: Rest,
+/// }
 ///
+/// let search: Search =
+///     serde_urlencoded::from_str("tag=a&query=rust&tag=b").unwrap();
+///
+/// assert_eq!(search.query, "rust");
 /// assert_eq!(
-///     serde_urlencoded::from_str::<Vec<(String, String)>>(
-///         "bread=baguette&cheese=comt%C3%A9&meat=ham&fat=butter"),
-///     Ok(meal));
+///     search.rest.0,
+///     [("tag".to_owned(), "a".to_owned()), ("tag".to_owned(), "b".to_owned())]);
 /// ```
-pub fn from_str<'de, T>(input: &'de str) -> Result<T, Error>
-where
-    T: de::Deserialize<'de>,
-{
-    from_bytes(input.as_bytes())
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rest(pub Vec<(String, String)>);
+
+impl<'de> de::Deserialize<'de> for Rest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct RestVisitor;
+
+        impl<'de> de::Visitor<'de> for RestVisitor {
+            type Value = Rest;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map of strings")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Rest, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                let mut pairs = Vec::new();
+                while let Some(pair) = map.next_entry()? {
+                    pairs.push(pair);
+                }
+                Ok(Rest(pairs))
+            }
+        }
+
+        deserializer.deserialize_map(RestVisitor)
+    }
 }
 
-/// Convenience function that reads all bytes from `reader` and deserializes
-/// them with `from_bytes`.
-pub fn from_reader<T, R>(mut reader: R) -> Result<T, Error>
-where
-    T: de::DeserializeOwned,
-    R: Read,
-{
-    let mut buf = vec![];
-    reader.read_to_end(&mut buf).map_err(|e| {
-        de::Error::custom(format_args!("could not read input: {}", e))
-    })?;
-    from_bytes(&buf)
+impl Serialize for Rest {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_map(self.0.iter().map(|(k, v)| (k, v)))
+    }
 }
 
-/// A deserializer for the `application/x-www-form-urlencoded` format.
-///
-/// * Supported top-level outputs are structs, maps and sequences of pairs,
-///   with or without a given length.
-///
-/// * Main `deserialize` methods defers to `deserialize_map`.
-///
-/// * Everything else but `deserialize_seq` and `deserialize_seq_fixed_size`
-///   defers to `deserialize`.
-pub struct Deserializer<'de> {
-    inner: MapDeserializer<'de, PartIterator<'de>, Error>,
+/// A key whose value was ignored during deserialization.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownKey<'de> {
+    key: Cow<'de, str>,
+    offset: Option<usize>,
 }
 
-impl<'de> Deserializer<'de> {
-    /// Returns a new `Deserializer`.
-    pub fn new(parser: UrlEncodedParse<'de>) -> Self {
-        Deserializer {
-            inner: MapDeserializer::new(PartIterator(parser)),
+impl<'de> UnknownKey<'de> {
+    /// Returns the decoded key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the byte offset of the pair in the input.
+    ///
+    /// This is `None` when the deserializer was built from an already
+    /// parsed `form_urlencoded::Parse` or from decoded pairs.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+}
+
+/// Map and sequence access to the pairs of a `Deserializer`.
+struct PairAccess<'de> {
+    iter: PartIterator<'de>,
+    value: Option<Part<'de>>,
+    count: usize,
+    key: Option<(Option<usize>, Text<'de>)>,
+    unknown_keys: Option<Vec<UnknownKey<'de>>>,
+    matching: KeyMatching,
+    fields: Option<&'static [&'static str]>,
+    prefix: Cow<'de, str>,
+}
+
+impl<'de> PairAccess<'de> {
+    fn new(iter: PartIterator<'de>) -> Self {
+        PairAccess {
+            iter,
+            prefix: Cow::Borrowed(""),
+            value: None,
+            count: 0,
+            key: None,
+            unknown_keys: None,
+            matching: KeyMatching::Exact,
+            fields: None,
+        }
+    }
+
+    /// Returns the next pair whose key has the prefix, stripped of it.
+    fn next_pair(&mut self) -> Option<(Option<usize>, Text<'de>, Text<'de>)> {
+        if self.prefix.is_empty() {
+            return self.iter.next();
+        }
+        let prefix = &self.prefix;
+        self.iter.by_ref().find_map(|(offset, key, value)| {
+            Some((offset, key.strip_prefix(prefix)?, value))
+        })
+    }
+
+    fn check_empty(&mut self) -> Result<(), Error> {
+        match self.next_pair() {
+            Some(_) => {
+                let remaining = 1 + iter::from_fn(|| self.next_pair()).count();
+                Err(de::Error::invalid_length(
+                    self.count + remaining,
+                    &ExpectedInMap(self.count),
+                ))
+            }
+            None => Ok(()),
         }
     }
+
+    fn end(mut self) -> Result<(), Error> {
+        self.check_empty()
+    }
 }
 
-impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+impl<'de> de::MapAccess<'de> for PairAccess<'de> {
     type Error = Error;
 
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.next_pair() {
+            Some((offset, key, value)) => {
+                self.count += 1;
+                self.value = Some(Part(value, self.matching));
+                if self.unknown_keys.is_some() {
+                    self.key = Some((offset, key.clone()));
+                }
+                let key = match self.fields {
+                    Some(fields) => self.matching.resolve(key, fields)?,
+                    None => key,
+                };
+                seed.deserialize(Part(key, self.matching)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .expect("MapAccess::next_value called before next_key");
+        let unknown_keys = match self.unknown_keys {
+            Some(ref mut unknown_keys) => unknown_keys,
+            None => return seed.deserialize(value),
+        };
+        let mut ignored = false;
+        let result = seed.deserialize(IgnoreTracker {
+            part: value,
+            ignored: &mut ignored,
+        });
+        if let (true, Some((offset, key))) = (ignored, self.key.take()) {
+            let key = match key.decode() {
+                key if self.prefix.is_empty() => key,
+                key => format!("{}{}", self.prefix, key).into(),
+            };
+            unknown_keys.push(UnknownKey { key, offset });
+        }
+        result
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for PairAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.next_pair() {
+            Some((_, key, value)) => {
+                self.count += 1;
+                let key = Part(key, self.matching);
+                let value = Part(value, self.matching);
+                seed.deserialize(PairDeserializer(key, value)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Deserializes a value, noting whether it was ignored.
+struct IgnoreTracker<'a, 'de> {
+    part: Part<'de>,
+    ignored: &'a mut bool,
+}
+
+macro_rules! forward_to_part {
+    ($($method:ident($($arg:ident: $ty:ty),*),)*) => {
+        $(
+            fn $method<V>(
+                self,
+                $($arg: $ty,)*
+                visitor: V,
+            ) -> Result<V::Value, Self::Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                self.part.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'a, 'de> de::Deserializer<'de> for IgnoreTracker<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_ignored_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        *self.ignored = true;
+        self.part.deserialize_ignored_any(visitor)
     }
 
-    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    forward_to_part! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(
+            name: &'static str,
+            fields: &'static [&'static str]
+        ),
+        deserialize_enum(
+            name: &'static str,
+            variants: &'static [&'static str]
+        ),
+        deserialize_identifier(),
+    }
+}
+
+/// A deserializer for a single unencoded value.
+///
+/// Values are deserialized exactly as the values of pairs are: booleans and
+/// numbers are parsed from the text, unit variants are matched by name and
+/// options are always `Some`.
+pub struct ValueDeserializer<'de> {
+    part: Part<'de>,
+}
+
+impl<'de> ValueDeserializer<'de> {
+    /// Returns a new `ValueDeserializer`.
+    pub fn new<V>(value: V) -> Self
+    where
+        V: Into<Cow<'de, str>>,
+    {
+        ValueDeserializer {
+            part: Part(Text::Decoded(value.into()), KeyMatching::Exact),
+        }
+    }
+}
+
+impl<'de> IntoDeserializer<'de> for ValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    forward_to_part! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(
+            name: &'static str,
+            fields: &'static [&'static str]
+        ),
+        deserialize_enum(
+            name: &'static str,
+            variants: &'static [&'static str]
+        ),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+}
+
+struct ExpectedInMap(usize);
+
+impl de::Expected for ExpectedInMap {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 1 {
+            formatter.write_str("1 element in map")
+        } else {
+            write!(formatter, "{} elements in map", self.0)
+        }
+    }
+}
+
+/// Deserializes a single pair as a 2-tuple.
+struct PairDeserializer<'de>(Part<'de>, Part<'de>);
+
+impl<'de> de::Deserializer<'de> for PairDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_map(self.inner)
+        self.deserialize_seq(visitor)
     }
 
     fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_seq(self.inner)
+        let mut pair = PairSeqAccess(Some(self.0), Some(self.1));
+        let value = visitor.visit_seq(&mut pair)?;
+        match pair.remaining() {
+            0 => Ok(value),
+            remaining => {
+                Err(de::Error::invalid_length(2, &ExpectedInSeq(2 - remaining)))
+            }
+        }
     }
 
-    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_tuple<V>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.inner.end()?;
-        visitor.visit_unit()
+        if len == 2 {
+            self.deserialize_seq(visitor)
+        } else {
+            Err(de::Error::invalid_length(2, &ExpectedInSeq(len)))
+        }
     }
 
     forward_to_deserialize_any! {
@@ -142,28 +1453,189 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
         option
         bytes
         byte_buf
+        unit
         unit_struct
         newtype_struct
         tuple_struct
+        map
         struct
         identifier
-        tuple
         enum
         ignored_any
     }
 }
 
-struct PartIterator<'de>(UrlEncodedParse<'de>);
+struct PairSeqAccess<'de>(Option<Part<'de>>, Option<Part<'de>>);
+
+impl<'de> PairSeqAccess<'de> {
+    fn remaining(&self) -> usize {
+        self.0.is_some() as usize + self.1.is_some() as usize
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for PairSeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.0.take().or_else(|| self.1.take()) {
+            Some(part) => seed.deserialize(part).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining())
+    }
+}
+
+struct ExpectedInSeq(usize);
+
+impl de::Expected for ExpectedInSeq {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 1 {
+            formatter.write_str("1 element in sequence")
+        } else {
+            write!(formatter, "{} elements in sequence", self.0)
+        }
+    }
+}
+
+/// A key or value, percent-decoded only once needed.
+#[derive(Clone)]
+enum Text<'de> {
+    /// Bytes straight from the input.
+    Encoded(&'de [u8], Decoding),
+    Decoded(Cow<'de, str>),
+}
+
+impl<'de> Text<'de> {
+    /// Returns the text if there is nothing to decode in it.
+    fn as_plain(&self) -> Option<&str> {
+        match *self {
+            Text::Encoded(bytes, decoding) => {
+                let escaped = |&b: &u8| {
+                    b == b'%' || b == b'+' && decoding == Decoding::Form
+                };
+                if bytes.iter().any(escaped) {
+                    return None;
+                }
+                str::from_utf8(bytes).ok()
+            }
+            Text::Decoded(ref text) => Some(text),
+        }
+    }
+
+    /// Returns the text without `prefix`, if it starts with it.
+    fn strip_prefix(self, prefix: &str) -> Option<Self> {
+        if let Text::Encoded(bytes, decoding) = self {
+            // A prefix with nothing to decode in it can be looked for in
+            // the encoded bytes, which must then be decoded to tell only if
+            // they are escaped.
+            if !prefix.contains(['%', '+']) {
+                if let Some(rest) = bytes.strip_prefix(prefix.as_bytes()) {
+                    return Some(Text::Encoded(rest, decoding));
+                }
+                if self.as_plain().is_some() {
+                    return None;
+                }
+            }
+        }
+        match self.decode() {
+            Cow::Borrowed(text) => text
+                .strip_prefix(prefix)
+                .map(|rest| Text::Decoded(rest.into())),
+            Cow::Owned(text) => text
+                .strip_prefix(prefix)
+                .map(|rest| Text::Decoded(String::from(rest).into())),
+        }
+    }
+
+    fn decode(self) -> Cow<'de, str> {
+        match self {
+            Text::Encoded(bytes, Decoding::Form) if bytes.contains(&b'+') => {
+                // Spaces and percent escapes are decoded in a single pass,
+                // and the decoded text is never longer than the input.
+                let mut decoded = Vec::with_capacity(bytes.len());
+                for (i, part) in bytes.split(|&b| b == b'+').enumerate() {
+                    if i > 0 {
+                        decoded.push(b' ');
+                    }
+                    decoded.extend(percent_decode(part));
+                }
+                match String::from_utf8(decoded) {
+                    Ok(text) => text.into(),
+                    Err(err) => String::from_utf8_lossy(err.as_bytes())
+                        .into_owned()
+                        .into(),
+                }
+            }
+            Text::Encoded(bytes, _) => {
+                percent_decode(bytes).decode_utf8_lossy()
+            }
+            Text::Decoded(text) => text,
+        }
+    }
+}
+
+enum PartIterator<'de> {
+    Raw {
+        input: &'de [u8],
+        offset: usize,
+        decoding: Decoding,
+    },
+    Parse(UrlEncodedParse<'de>),
+    Pairs(Box<dyn Iterator<Item = (Cow<'de, str>, Cow<'de, str>)> + 'de>),
+}
 
 impl<'de> Iterator for PartIterator<'de> {
-    type Item = (Part<'de>, Part<'de>);
+    /// The pair, preceded by its byte offset in the input if known.
+    type Item = (Option<usize>, Text<'de>, Text<'de>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.0.next().map(|(k, v)| (Part(k), Part(v)))
+        match *self {
+            // Only the bounds of the pairs are found here, keys and values
+            // are decoded later on, if ever.
+            PartIterator::Raw {
+                input,
+                ref mut offset,
+                decoding,
+            } => loop {
+                let start = *offset;
+                let rest =
+                    input.get(start..).filter(|rest| !rest.is_empty())?;
+                let len =
+                    rest.iter().position(|&b| b == b'&').unwrap_or(rest.len());
+                *offset += len + 1;
+                let segment = &rest[..len];
+                if segment.is_empty() {
+                    continue;
+                }
+                let (k, v) = match segment.iter().position(|&b| b == b'=') {
+                    Some(i) => (&segment[..i], &segment[i + 1..]),
+                    None => (segment, &[][..]),
+                };
+                let k = Text::Encoded(k, decoding);
+                let v = Text::Encoded(v, decoding);
+                return Some((Some(start), k, v));
+            },
+            PartIterator::Parse(ref mut parse) => parse
+                .next()
+                .map(|(k, v)| (None, Text::Decoded(k), Text::Decoded(v))),
+            PartIterator::Pairs(ref mut pairs) => pairs
+                .next()
+                .map(|(k, v)| (None, Text::Decoded(k), Text::Decoded(v))),
+        }
     }
 }
 
-struct Part<'de>(Cow<'de, str>);
+/// A key or value, with the policy used to match it against variant names.
+struct Part<'de>(Text<'de>, KeyMatching);
 
 impl<'de> IntoDeserializer<'de> for Part<'de> {
     type Deserializer = Self;
@@ -179,7 +1651,12 @@ macro_rules! forward_parsed_value {
             fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
                 where V: de::Visitor<'de>
             {
-                match self.0.parse::<$ty>() {
+                // Numbers with nothing to decode are parsed in place.
+                let parsed = match self.0.as_plain() {
+                    Some(text) => text.parse::<$ty>(),
+                    None => self.0.decode().parse::<$ty>(),
+                };
+                match parsed {
                     Ok(val) => val.into_deserializer().$method(visitor),
                     Err(e) => Err(de::Error::custom(e))
                 }
@@ -195,12 +1672,22 @@ impl<'de> de::Deserializer<'de> for Part<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.0 {
+        match self.0.decode() {
             Cow::Borrowed(value) => visitor.visit_borrowed_str(value),
             Cow::Owned(value) => visitor.visit_string(value),
         }
     }
 
+    fn deserialize_ignored_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
@@ -211,13 +1698,14 @@ impl<'de> de::Deserializer<'de> for Part<'de> {
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
-        _variants: &'static [&'static str],
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_enum(ValueEnumAccess(self.0))
+        let variant = self.1.resolve(self.0, variants)?.decode();
+        visitor.visit_enum(ValueEnumAccess(variant))
     }
 
     fn deserialize_newtype_struct<V>(
@@ -243,7 +1731,6 @@ impl<'de> de::Deserializer<'de> for Part<'de> {
         struct
         identifier
         tuple
-        ignored_any
         seq
         map
     }
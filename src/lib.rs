@@ -1,12 +1,53 @@
 //! `x-www-form-urlencoded` meets Serde
 
+#![no_std]
 #![warn(unused_extern_crates)]
 #![forbid(unsafe_code)]
 
+#[cfg(not(feature = "alloc"))]
+compile_error!("serde_urlencoded requires either the `std` or `alloc` feature");
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 pub mod de;
 pub mod ser;
 
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use crate::de::from_reader;
+#[doc(inline)]
+pub use crate::de::{
+    from_bytes, from_bytes_into, from_str, from_str_into, from_url_query_bytes,
+    from_url_query_str, from_value_str, Deserializer,
+};
+#[cfg(feature = "std")]
+#[doc(inline)]
+pub use crate::ser::to_writer;
+#[doc(inline)]
+pub use crate::ser::{
+    display, for_each_pair, to_fmt, to_pairs, to_split_strings, to_string,
+    to_value_string, to_vec, try_display, QueryBuilder, Rewriter, Serializer,
+};
+
+#[cfg(feature = "url")]
+#[doc(inline)]
+pub use crate::de::from_url;
+#[cfg(feature = "url")]
+#[doc(inline)]
+pub use crate::ser::append_to_url;
+
+#[cfg(feature = "http")]
+#[doc(inline)]
+pub use crate::de::{from_request, from_uri};
+#[cfg(feature = "http")]
+#[doc(inline)]
+pub use crate::ser::to_request;
+
+#[cfg(feature = "bytes")]
 #[doc(inline)]
-pub use crate::de::{from_bytes, from_reader, from_str, Deserializer};
+pub use crate::de::from_shared_bytes;
+#[cfg(feature = "bytes")]
 #[doc(inline)]
-pub use crate::ser::{to_string, Serializer};
+pub use crate::ser::to_bytes_mut;
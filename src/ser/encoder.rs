@@ -0,0 +1,342 @@
+use crate::ser::{Error, Output};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+/// An `Output` percent-encoding pairs into a `fmt::Write` as it goes.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_urlencoded::ser::{Encoder, Encoding, Serializer};
+///
+/// let mut encoder = Encoder::new(String::new()).encoding(Encoding::query());
+/// [("q", "a b+c")].serialize(Serializer::new(&mut encoder)).unwrap();
+///
+/// assert_eq!(encoder.into_inner(), "q=a%20b%2Bc");
+/// ```
+#[derive(Debug)]
+pub struct Encoder<W> {
+    writer: W,
+    encoding: Encoding,
+    first: bool,
+}
+
+impl<W: fmt::Write> Encoder<W> {
+    /// Returns a new `Encoder` writing into `writer` with form encoding.
+    pub fn new(writer: W) -> Self {
+        Encoder {
+            writer,
+            encoding: Encoding::default(),
+            first: true,
+        }
+    }
+
+    /// Sets how keys and values are encoded.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_encoded(&mut self, input: &str) -> fmt::Result {
+        let mut start = 0;
+        for (i, &byte) in input.as_bytes().iter().enumerate() {
+            if self.encoding.is_safe(byte) {
+                continue;
+            }
+            // Either all the bytes of a non-ASCII character are safe or none
+            // is, so a non-empty run of safe bytes starts and ends at
+            // character boundaries.
+            if start < i {
+                self.writer.write_str(&input[start..i])?;
+            }
+            start = i + 1;
+            if byte == b' ' && self.encoding.space_as_plus {
+                self.writer.write_char('+')?;
+            } else {
+                self.write_escaped(byte)?;
+            }
+        }
+        if start < input.len() {
+            self.writer.write_str(&input[start..])?;
+        }
+        Ok(())
+    }
+
+    fn write_escaped(&mut self, byte: u8) -> fmt::Result {
+        let hex = if self.encoding.lowercase_hex {
+            b"0123456789abcdef"
+        } else {
+            b"0123456789ABCDEF"
+        };
+        self.writer.write_char('%')?;
+        self.writer
+            .write_char(char::from(hex[usize::from(byte >> 4)]))?;
+        self.writer
+            .write_char(char::from(hex[usize::from(byte & 0xf)]))
+    }
+
+    fn write_pair(&mut self, key: &str, value: Option<&str>) -> fmt::Result {
+        if self.first {
+            self.first = false;
+        } else {
+            self.writer.write_char('&')?;
+        }
+        self.write_encoded(key)?;
+        if let Some(value) = value {
+            self.writer.write_char('=')?;
+            self.write_encoded(value)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: fmt::Write> Output for Encoder<W> {
+    fn append_pair(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        self.write_pair(key, Some(value)).map_err(Error::Fmt)
+    }
+
+    fn append_key_only(&mut self, key: &str) -> Result<(), Error> {
+        self.write_pair(key, None).map_err(Error::Fmt)
+    }
+
+    fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+}
+
+/// How an `Encoder` percent-encodes keys and values.
+///
+/// Letters, digits and the characters that are never special in a URL are
+/// always left as is, every other byte is percent-encoded unless the
+/// encoding says otherwise.
+///
+/// Whatever the encoding, the characters that delimit or escape pairs (`&`,
+/// `=`, `%` and `#`), `+`, which form decoding turns into a space, spaces
+/// and ASCII control characters are never left as is. The output of a form
+/// encoding thus decodes back to the same pairs with [`Decoding::Form`],
+/// and that of a query encoding with both [`Decoding::Form`] and
+/// [`Decoding::Query`].
+///
+/// ```
+/// use serde_urlencoded::ser::{Encoder, Encoding, Output};
+///
+/// let encoding = Encoding::query().keep(",:/@").iri(true);
+/// let mut encoder = Encoder::new(String::new()).encoding(encoding);
+/// encoder.append_pair("path", "/café/a,b").unwrap();
+/// encoder.append_pair("mail", "nox@example.com").unwrap();
+///
+/// assert_eq!(encoder.into_inner(), "path=/café/a,b&mail=nox@example.com");
+/// ```
+///
+/// [`Decoding::Form`]: crate::de::Decoding::Form
+/// [`Decoding::Query`]: crate::de::Decoding::Query
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Encoding {
+    /// The ASCII bytes that are left unescaped, as a bit set.
+    safe: u128,
+    space_as_plus: bool,
+    iri: bool,
+    lowercase_hex: bool,
+}
+
+impl Encoding {
+    /// The `application/x-www-form-urlencoded` encoding of HTML forms.
+    ///
+    /// Spaces are written as `+`, and only `*`, `-`, `.` and `_` are left
+    /// unescaped besides letters and digits. This is the default.
+    pub fn form() -> Self {
+        Encoding {
+            safe: ascii_set(b"*-._"),
+            space_as_plus: true,
+            iri: false,
+            lowercase_hex: false,
+        }
+    }
+
+    /// The query string encoding of RFC 3986.
+    ///
+    /// Spaces are written as `%20`, and only the unreserved characters `-`,
+    /// `.`, `_` and `~` are left unescaped besides letters and digits.
+    pub fn query() -> Self {
+        Encoding {
+            safe: ascii_set(b"-._~"),
+            space_as_plus: false,
+            iri: false,
+            lowercase_hex: false,
+        }
+    }
+
+    /// Leaves the given ASCII characters unescaped, such as `,:/@` for more
+    /// readable URLs.
+    ///
+    /// Only the characters that RFC 3986 allows as is in a query and that do
+    /// not delimit pairs are accepted: the sub-delimiters `!`, `$`, `'`,
+    /// `(`, `)`, `*`, `,` and `;`, then `:`, `@`, `/` and `?`, and the
+    /// unreserved `-`, `.`, `_` and `~`. Some servers treat `;` as a pair
+    /// separator.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chars` contains any other character, such as `&`, `=`,
+    /// `+`, `%`, `#`, `"`, `<`, `>`, `[`, `]`, `{`, `}`, `|`, `\`, `^`,
+    /// `` ` ``, a space or a non-ASCII character.
+    pub fn keep(mut self, chars: &str) -> Self {
+        for byte in chars.bytes() {
+            assert!(
+                byte.is_ascii_alphanumeric()
+                    || b"!$'()*,;:@/?-._~".contains(&byte),
+                "{:?} cannot be left unescaped",
+                char::from(byte)
+            );
+            self.safe |= 1 << byte;
+        }
+        self
+    }
+
+    /// Leaves non-ASCII characters unescaped, as in an IRI (RFC 3987).
+    ///
+    /// The output is then only a valid URL once converted by a client that
+    /// understands IRIs, such as a web browser.
+    pub fn iri(mut self, iri: bool) -> Self {
+        self.iri = iri;
+        self
+    }
+
+    /// Writes percent escapes with lowercase hexadecimal digits, as in
+    /// `%c3%a9`, instead of uppercase ones.
+    ///
+    /// RFC 3986 recommends uppercase, but both decode the same.
+    pub fn lowercase_hex(mut self, lowercase: bool) -> Self {
+        self.lowercase_hex = lowercase;
+        self
+    }
+
+    /// Returns the length of an encoded pair, or of a bare key.
+    pub(crate) fn pair_len(&self, key: &str, value: Option<&str>) -> usize {
+        self.encoded_len(key)
+            + value.map_or(0, |value| 1 + self.encoded_len(value))
+    }
+
+    fn encoded_len(&self, input: &str) -> usize {
+        input
+            .bytes()
+            .map(|byte| {
+                if self.is_safe(byte) || byte == b' ' && self.space_as_plus {
+                    1
+                } else {
+                    3
+                }
+            })
+            .sum()
+    }
+
+    fn is_safe(&self, byte: u8) -> bool {
+        if byte < 128 {
+            self.safe & (1 << byte) != 0
+        } else {
+            self.iri
+        }
+    }
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::form()
+    }
+}
+
+fn ascii_set(extra: &[u8]) -> u128 {
+    let mut set = 0;
+    for byte in (b'0'..=b'9').chain(b'A'..=b'Z').chain(b'a'..=b'z') {
+        set |= 1 << byte;
+    }
+    for &byte in extra {
+        set |= 1 << byte;
+    }
+    set
+}
+
+/// An `Output` encoding into a buffer, reserving space in it for the pairs
+/// to come.
+pub struct BufferOutput<W>(pub Encoder<W>);
+
+/// A guess of the encoded length of a pair, separator included.
+const PAIR_LEN_ESTIMATE: usize = 16;
+
+impl<W: Buffer> Output for BufferOutput<W> {
+    fn append_pair(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        self.0.append_pair(key, value)
+    }
+
+    fn append_key_only(&mut self, key: &str) -> Result<(), Error> {
+        self.0.append_key_only(key)
+    }
+
+    fn encoding(&self) -> Encoding {
+        self.0.encoding
+    }
+
+    fn reserve(&mut self, pairs: usize) {
+        let additional = pairs.saturating_mul(PAIR_LEN_ESTIMATE);
+        self.0.writer.reserve(additional);
+    }
+}
+
+/// A `fmt::Write` that can grow ahead of the writes.
+pub trait Buffer: fmt::Write {
+    fn reserve(&mut self, additional: usize);
+}
+
+impl Buffer for String {
+    fn reserve(&mut self, additional: usize) {
+        String::reserve(self, additional);
+    }
+}
+
+impl Buffer for VecWriter {
+    fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Buffer for bytes::BytesMut {
+    fn reserve(&mut self, additional: usize) {
+        bytes::BytesMut::reserve(self, additional);
+    }
+}
+
+/// A `fmt::Write` appending to a byte buffer.
+pub struct VecWriter(pub Vec<u8>);
+
+impl fmt::Write for VecWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// A `fmt::Write` writing to an `io::Write`, keeping the I/O error that
+/// made it fail.
+#[cfg(feature = "std")]
+pub struct IoWriter<W> {
+    pub inner: W,
+    pub error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
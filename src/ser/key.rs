@@ -1,12 +1,11 @@
-use crate::ser::part::Sink;
-use crate::ser::Error;
+use crate::ser::part::{PartBuffer, Sink};
+use crate::ser::{Error, Kind, Location};
+use core::ops::Deref;
 use serde::Serialize;
-use std::borrow::Cow;
-use std::ops::Deref;
 
 pub enum Key<'key> {
     Static(&'static str),
-    Dynamic(Cow<'key, str>),
+    Dynamic(&'key str),
 }
 
 impl<'key> Deref for Key<'key> {
@@ -15,18 +14,54 @@ impl<'key> Deref for Key<'key> {
     fn deref(&self) -> &str {
         match *self {
             Key::Static(key) => key,
-            Key::Dynamic(ref key) => key,
+            Key::Dynamic(key) => key,
         }
     }
 }
 
-impl<'key> From<Key<'key>> for Cow<'static, str> {
-    fn from(key: Key<'key>) -> Self {
-        match key {
-            Key::Static(key) => key.into(),
-            Key::Dynamic(key) => key.into_owned().into(),
+/// The key of a pair whose value is yet to be serialized, copying dynamic
+/// keys into a buffer reused from one pair to the next.
+pub struct KeyBuffer {
+    state: KeyState,
+    buffer: PartBuffer,
+}
+
+enum KeyState {
+    Empty,
+    Static(&'static str),
+    Buffered,
+}
+
+impl KeyBuffer {
+    pub fn new() -> Self {
+        KeyBuffer {
+            state: KeyState::Empty,
+            buffer: PartBuffer::new(),
+        }
+    }
+
+    pub fn set(&mut self, key: Key<'_>) {
+        self.state = match key {
+            Key::Static(key) => KeyState::Static(key),
+            Key::Dynamic(key) => {
+                self.buffer.clear();
+                self.buffer.push_str(key);
+                KeyState::Buffered
+            }
+        };
+    }
+
+    pub fn get(&self) -> Option<&str> {
+        match self.state {
+            KeyState::Empty => None,
+            KeyState::Static(key) => Some(key),
+            KeyState::Buffered => Some(self.buffer.as_str()),
         }
     }
+
+    pub fn clear(&mut self) {
+        self.state = KeyState::Empty;
+    }
 }
 
 pub struct KeySink<End> {
@@ -53,25 +88,25 @@ where
     }
 
     fn serialize_str(self, value: &str) -> Result<Ok, Error> {
-        (self.end)(Key::Dynamic(value.into()))
-    }
-
-    fn serialize_string(self, value: String) -> Result<Ok, Error> {
-        (self.end)(Key::Dynamic(value.into()))
+        (self.end)(Key::Dynamic(value))
     }
 
     fn serialize_none(self) -> Result<Ok, Error> {
-        Err(self.unsupported())
+        Err(self.unsupported(Kind::Option))
     }
 
     fn serialize_some<T: ?Sized + Serialize>(
         self,
         _value: &T,
     ) -> Result<Ok, Error> {
-        Err(self.unsupported())
+        Err(self.unsupported(Kind::Option))
+    }
+
+    fn unsupported(self, kind: Kind) -> Error {
+        Error::unsupported(kind, Location::Key, None)
     }
 
-    fn unsupported(self) -> Error {
-        Error::Custom("unsupported key".into())
+    fn non_finite(self) -> Error {
+        Error::non_finite(Location::Key, None)
     }
 }
@@ -1,17 +1,30 @@
 //! Serialization support for the `application/x-www-form-urlencoded` format.
 
+mod encoder;
 mod key;
 mod pair;
 mod part;
+mod query;
+mod rewrite;
 mod value;
 
+pub use self::encoder::{Encoder, Encoding};
+pub use self::query::{Collisions, QueryBuilder};
+pub use self::rewrite::Rewriter;
+
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::str;
 use form_urlencoded::Serializer as UrlEncodedSerializer;
 use form_urlencoded::Target as UrlEncodedTarget;
 use serde::ser;
-use std::borrow::Cow;
-use std::error;
-use std::fmt;
-use std::str;
+#[cfg(feature = "std")]
+use std::io;
 
 /// Serializes a value into a `application/x-www-form-urlencoded` `String` buffer.
 ///
@@ -28,9 +41,413 @@ use std::str;
 ///     Ok("bread=baguette&cheese=comt%C3%A9&meat=ham&fat=butter".to_owned()));
 /// ```
 pub fn to_string<T: ser::Serialize>(input: T) -> Result<String, Error> {
-    let mut urlencoder = UrlEncodedSerializer::new("".to_owned());
-    input.serialize(Serializer::new(&mut urlencoder))?;
-    Ok(urlencoder.finish())
+    let mut output = encoder::BufferOutput(Encoder::new(String::new()));
+    input.serialize(Serializer::new(&mut output))?;
+    Ok(output.0.into_inner())
+}
+
+/// Serializes a value into a `application/x-www-form-urlencoded` byte
+/// buffer.
+///
+/// ```
+/// assert_eq!(
+///     serde_urlencoded::to_vec(&[("cheese", "comté")]),
+///     Ok(b"cheese=comt%C3%A9".to_vec()));
+/// ```
+pub fn to_vec<T: ser::Serialize>(input: T) -> Result<Vec<u8>, Error> {
+    let writer = encoder::VecWriter(Vec::new());
+    let mut output = encoder::BufferOutput(Encoder::new(writer));
+    input.serialize(Serializer::new(&mut output))?;
+    Ok(output.0.into_inner().0)
+}
+
+/// Serializes a value as `application/x-www-form-urlencoded` into a
+/// `fmt::Write`, such as a `fmt::Formatter`.
+///
+/// Pairs are encoded and written piece by piece, so a failing serialization
+/// may leave part of the output written.
+///
+/// ```
+/// let mut query = String::from("/search?");
+/// serde_urlencoded::to_fmt(&mut query, &[("q", "a b")]).unwrap();
+///
+/// assert_eq!(query, "/search?q=a+b");
+/// ```
+pub fn to_fmt<W, T>(writer: &mut W, input: T) -> Result<(), Error>
+where
+    W: ?Sized + fmt::Write,
+    T: ser::Serialize,
+{
+    let mut encoder = Encoder::new(writer);
+    input.serialize(Serializer::new(&mut encoder))?;
+    Ok(())
+}
+
+/// Serializes a value as `application/x-www-form-urlencoded` into an
+/// `io::Write`.
+///
+/// Pairs are encoded and written piece by piece without any intermediate
+/// buffer, so unbuffered writers such as sockets should be wrapped in an
+/// `io::BufWriter`. A failing serialization may leave part of the output
+/// written.
+///
+/// ```
+/// let mut body = Vec::new();
+/// serde_urlencoded::to_writer(&mut body, &[("user", "nox")]).unwrap();
+///
+/// assert_eq!(body, b"user=nox");
+/// ```
+#[cfg(feature = "std")]
+pub fn to_writer<W, T>(writer: W, input: T) -> Result<(), Error>
+where
+    W: io::Write,
+    T: ser::Serialize,
+{
+    let mut encoder = Encoder::new(encoder::IoWriter {
+        inner: writer,
+        error: None,
+    });
+    let result = input.serialize(Serializer::new(&mut encoder)).map(|_| ());
+    match (result, encoder.into_inner().error) {
+        (Err(_), Some(err)) => Err(Error::Io(err.into())),
+        (result, _) => result,
+    }
+}
+
+/// Returns a `fmt::Display` adapter serializing a value as
+/// `application/x-www-form-urlencoded` when formatted.
+///
+/// Nothing is serialized until the adapter is formatted, and pairs are
+/// written straight into the formatter. A serialization error is reported as
+/// a `fmt::Error`, which makes `format!` and `ToString::to_string` panic, so
+/// use [`try_display`] when the value may fail to serialize.
+///
+/// ```
+/// let params = &[("q", "a b"), ("page", "2")];
+///
+/// assert_eq!(
+///     format!("/search?{}", serde_urlencoded::display(params)),
+///     "/search?q=a+b&page=2");
+/// ```
+pub fn display<T: ?Sized + ser::Serialize>(input: &T) -> Display<'_, T> {
+    Display { input }
+}
+
+/// Like [`display`], but checks first that the value serializes, reporting
+/// the actual error if it does not.
+///
+/// The check serializes the value without encoding or writing it, and
+/// formatting the adapter serializes it again.
+///
+/// ```
+/// let params = &[("q", Ok("rust")), ("page", Err(2))];
+///
+/// assert!(serde_urlencoded::try_display(params).is_err());
+/// ```
+pub fn try_display<T: ?Sized + ser::Serialize>(
+    input: &T,
+) -> Result<Display<'_, T>, Error> {
+    for_each_pair(input, |_, _| ())?;
+    Ok(display(input))
+}
+
+/// A `fmt::Display` adapter returned by [`display`] and [`try_display`].
+#[derive(Debug)]
+pub struct Display<'a, T: ?Sized> {
+    input: &'a T,
+}
+
+impl<'a, T: ?Sized> Clone for Display<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T: ?Sized> Copy for Display<'a, T> {}
+
+impl<'a, T: ?Sized + ser::Serialize> fmt::Display for Display<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        to_fmt(f, self.input).map_err(|_| fmt::Error)
+    }
+}
+
+/// Serializes a single value into its unencoded text representation.
+///
+/// Values are written exactly as they would be in a pair, so this accepts
+/// the same types as the values of a struct or map. `None` gives an empty
+/// string.
+///
+/// ```
+/// use serde_urlencoded::ser::to_value_string;
+///
+/// assert_eq!(to_value_string(42), Ok("42".to_owned()));
+/// assert_eq!(to_value_string(Some(true)), Ok("true".to_owned()));
+/// assert!(to_value_string(("a", "b")).is_err());
+/// ```
+pub fn to_value_string<T: ser::Serialize>(input: T) -> Result<String, Error> {
+    let options = Options::default();
+    input.serialize(part::PartSerializer::new(value::StringSink, &options))
+}
+
+/// Appends the pairs of a value to the query of a `Url`, keeping the
+/// parameters already there.
+///
+/// The URL is left untouched if serialization fails, and no empty query is
+/// added to it if there is nothing to append.
+///
+/// ```
+/// let mut url = url::Url::parse("https://example.com/?page=2#top").unwrap();
+/// serde_urlencoded::append_to_url(&mut url, &[("q", "comté")]).unwrap();
+///
+/// assert_eq!(url.as_str(), "https://example.com/?page=2&q=comt%C3%A9#top");
+/// ```
+#[cfg(feature = "url")]
+pub fn append_to_url<T: ser::Serialize>(
+    url: &mut url::Url,
+    input: T,
+) -> Result<(), Error> {
+    let original = url.query().map(String::from);
+    let result = {
+        let mut urlencoder = url.query_pairs_mut();
+        input
+            .serialize(Serializer::new(&mut urlencoder))
+            .map(|_| ())
+    };
+    if result.is_err() {
+        url.set_query(original.as_deref());
+    } else if original.is_none() && url.query() == Some("") {
+        url.set_query(None);
+    }
+    result
+}
+
+/// Finishes an `http::request::Builder` with a value serialized by
+/// `to_string` as its body, setting the `Content-Type` header to
+/// `application/x-www-form-urlencoded`.
+///
+/// ```
+/// let builder = http::Request::post("https://example.com/login");
+/// let request =
+///     serde_urlencoded::to_request(builder, &[("user", "nox")]).unwrap();
+///
+/// assert_eq!(
+///     request.headers()["content-type"],
+///     "application/x-www-form-urlencoded");
+/// assert_eq!(request.body(), "user=nox");
+/// ```
+#[cfg(feature = "http")]
+pub fn to_request<T: ser::Serialize>(
+    builder: http::request::Builder,
+    input: T,
+) -> Result<http::Request<String>, Error> {
+    let body = to_string(input)?;
+    builder
+        .header(
+            http::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(body)
+        .map_err(|err| {
+            Error::Custom(format!("invalid request: {}", err).into())
+        })
+}
+
+/// Serializes a value into a `bytes::BytesMut` buffer.
+///
+/// ```
+/// let buf = serde_urlencoded::to_bytes_mut(&[("user", "nox")]).unwrap();
+///
+/// assert_eq!(&buf[..], b"user=nox");
+/// ```
+#[cfg(feature = "bytes")]
+pub fn to_bytes_mut<T: ser::Serialize>(
+    input: T,
+) -> Result<bytes::BytesMut, Error> {
+    let writer = bytes::BytesMut::new();
+    let mut output = encoder::BufferOutput(Encoder::new(writer));
+    input.serialize(Serializer::new(&mut output))?;
+    Ok(output.0.into_inner())
+}
+
+/// Serializes a value into a list of unencoded key-value pairs.
+///
+/// ```
+/// let meal = &[("bread", "baguette"), ("cheese", "comté")];
+///
+/// assert_eq!(
+///     serde_urlencoded::to_pairs(meal),
+///     Ok(vec![
+///         ("bread".to_owned(), "baguette".to_owned()),
+///         ("cheese".to_owned(), "comté".to_owned()),
+///     ]));
+/// ```
+pub fn to_pairs<T: ser::Serialize>(
+    input: T,
+) -> Result<Vec<(String, String)>, Error> {
+    let mut pairs = Vec::new();
+    input.serialize(Serializer::new(&mut pairs))?;
+    Ok(pairs)
+}
+
+/// Serializes a value into as many strings encoded with `encoding` as
+/// needed for each of them to be at most `max_len` bytes long, spreading the
+/// pairs whose key is `repeated_key` across them.
+///
+/// Every other pair is repeated in each string, at its original position
+/// relative to the first pair with the repeated key. Fails with
+/// [`Error::TooLong`] if these pairs and a single repeated pair do not fit.
+///
+/// Apart from the encoding, the pairs are serialized with the default
+/// options of [`Serializer`]: keys have no prefix, pairs keep their order,
+/// `None` values are skipped and floats use [`FloatFormat::Shortest`].
+///
+/// ```
+/// use serde_urlencoded::ser::Encoding;
+///
+/// let query = &[("lang", "fr"), ("id", "1"), ("id", "2"), ("id", "3")];
+///
+/// assert_eq!(
+///     serde_urlencoded::to_split_strings(query, "id", Encoding::form(), 17),
+///     Ok(vec![
+///         "lang=fr&id=1&id=2".to_owned(),
+///         "lang=fr&id=3".to_owned(),
+///     ]));
+/// ```
+pub fn to_split_strings<T: ser::Serialize>(
+    input: T,
+    repeated_key: &str,
+    encoding: Encoding,
+    max_len: usize,
+) -> Result<Vec<String>, Error> {
+    let pairs = to_pairs(input)?;
+    let first = pairs.iter().position(|(key, _)| key == repeated_key);
+    let (before, after) = pairs.split_at(first.unwrap_or(pairs.len()));
+    let (repeated, after): (Vec<_>, Vec<_>) =
+        after.iter().partition(|(key, _)| key == repeated_key);
+
+    // Every pair is counted with a separator, one more than needed.
+    let pair_len = |(key, value): &(String, String)| {
+        1 + encoding.pair_len(key, Some(value))
+    };
+    let fixed_len = before.iter().chain(after.iter().copied()).map(pair_len);
+    let fixed_len = fixed_len.sum::<usize>();
+
+    let mut chunks: Vec<Vec<&(String, String)>> = Vec::new();
+    let mut chunk_len = 0;
+    for pair in repeated {
+        let len = pair_len(pair);
+        match chunks.last_mut() {
+            Some(chunk) if fixed_len + chunk_len + len <= max_len + 1 => {
+                chunk.push(pair);
+                chunk_len += len;
+            }
+            _ => {
+                chunks.push(Vec::from([pair]));
+                chunk_len = len;
+            }
+        }
+    }
+    if chunks.is_empty() {
+        chunks.push(Vec::new());
+    }
+
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let pairs = before.iter().chain(chunk).chain(after.iter().copied());
+            let mut encoder = Encoder::new(String::new()).encoding(encoding);
+            let serializer = Serializer::new(&mut encoder).max_len(max_len);
+            ser::Serializer::collect_seq(serializer, pairs)?;
+            Ok(encoder.into_inner())
+        })
+        .collect()
+}
+
+/// Serializes a value, calling `f` with each unencoded key-value pair as
+/// soon as it is produced instead of collecting them.
+///
+/// Pairs already passed to `f` are not taken back if serialization fails
+/// later on.
+///
+/// ```
+/// let mut query = Vec::new();
+/// serde_urlencoded::for_each_pair(&[("lang", "fr"), ("q", "a b")], |k, v| {
+///     query.push(format!("{}:{}", k, v));
+/// })
+/// .unwrap();
+///
+/// assert_eq!(query, ["lang:fr", "q:a b"]);
+/// ```
+pub fn for_each_pair<T, F>(input: T, f: F) -> Result<(), Error>
+where
+    T: ser::Serialize,
+    F: FnMut(&str, &str),
+{
+    input.serialize(Serializer::new(&mut FnOutput(f)))?;
+    Ok(())
+}
+
+/// A destination for the key-value pairs produced by a `Serializer`.
+///
+/// Pairs are passed unencoded; implementations are responsible for any
+/// encoding they need.
+pub trait Output {
+    /// Appends a key-value pair.
+    fn append_pair(&mut self, key: &str, value: &str) -> Result<(), Error>;
+
+    /// Appends a key without a value.
+    ///
+    /// Outputs that cannot tell a bare key from an empty value append the
+    /// key with an empty value by default.
+    fn append_key_only(&mut self, key: &str) -> Result<(), Error> {
+        self.append_pair(key, "")
+    }
+
+    /// Returns the encoding used to measure the length of the output for
+    /// [`Serializer::max_len`], which is form encoding by default.
+    fn encoding(&self) -> Encoding {
+        Encoding::form()
+    }
+
+    /// Hints that about `pairs` more pairs are going to be appended, so that
+    /// space can be reserved for them. Does nothing by default.
+    fn reserve(&mut self, pairs: usize) {
+        let _ = pairs;
+    }
+}
+
+impl<'input, Target: UrlEncodedTarget> Output
+    for UrlEncodedSerializer<'input, Target>
+{
+    fn append_pair(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        UrlEncodedSerializer::append_pair(self, key, value);
+        Ok(())
+    }
+
+    fn append_key_only(&mut self, key: &str) -> Result<(), Error> {
+        UrlEncodedSerializer::append_key_only(self, key);
+        Ok(())
+    }
+}
+
+impl Output for Vec<(String, String)> {
+    fn append_pair(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        self.push((key.into(), value.into()));
+        Ok(())
+    }
+
+    fn reserve(&mut self, pairs: usize) {
+        Vec::reserve(self, pairs);
+    }
+}
+
+struct FnOutput<F>(F);
+
+impl<F: FnMut(&str, &str)> Output for FnOutput<F> {
+    fn append_pair(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        (self.0)(key, value);
+        Ok(())
+    }
 }
 
 /// A serializer for the `application/x-www-form-urlencoded` format.
@@ -42,59 +459,420 @@ pub fn to_string<T: ser::Serialize>(input: T) -> Result<String, Error> {
 ///   unit structs and unit variants.
 ///
 /// * Newtype structs defer to their inner values.
-pub struct Serializer<'input, 'output, Target: UrlEncodedTarget> {
-    urlencoder: &'output mut UrlEncodedSerializer<'input, Target>,
+pub struct Serializer<'output, O: Output> {
+    output: &'output mut O,
+    options: Options,
 }
 
-impl<'input, 'output, Target: 'output + UrlEncodedTarget>
-    Serializer<'input, 'output, Target>
-{
-    /// Returns a new `Serializer`.
-    pub fn new(
-        urlencoder: &'output mut UrlEncodedSerializer<'input, Target>,
-    ) -> Self {
-        Serializer { urlencoder }
+impl<'output, O: 'output + Output> Serializer<'output, O> {
+    /// Returns a new `Serializer` appending pairs to the given output, such
+    /// as a `form_urlencoded::Serializer`.
+    pub fn new(output: &'output mut O) -> Self {
+        Serializer {
+            output,
+            options: Options::default(),
+        }
+    }
+
+    /// Sets how floating point numbers are formatted.
+    ///
+    /// ```
+    /// use serde_urlencoded::ser::{FloatFormat, Serializer};
+    /// use serde::Serialize;
+    ///
+    /// let mut urlencoder = form_urlencoded::Serializer::new(String::new());
+    /// let serializer = Serializer::new(&mut urlencoder)
+    ///     .float_format(FloatFormat::Minimal);
+    /// [("lat", 48.0), ("big", 1e20)].serialize(serializer).unwrap();
+    ///
+    /// assert_eq!(urlencoder.finish(), "lat=48&big=100000000000000000000");
+    /// ```
+    pub fn float_format(mut self, format: FloatFormat) -> Self {
+        self.options.float_format = format;
+        self
+    }
+
+    /// Makes `NaN` and infinite floats fail with [`Error::NonFinite`]
+    /// instead of writing them as `NaN`, `inf` and `-inf`.
+    pub fn reject_non_finite(mut self, reject: bool) -> Self {
+        self.options.reject_non_finite = reject;
+        self
+    }
+
+    /// Sets the order in which pairs are written.
+    ///
+    /// ```
+    /// use serde_urlencoded::ser::{PairOrder, Serializer};
+    /// use serde::Serialize;
+    ///
+    /// let mut urlencoder = form_urlencoded::Serializer::new(String::new());
+    /// let serializer =
+    ///     Serializer::new(&mut urlencoder).pair_order(PairOrder::ByKey);
+    /// [("b", 1), ("a", 2), ("b", 0)].serialize(serializer).unwrap();
+    ///
+    /// assert_eq!(urlencoder.finish(), "a=2&b=1&b=0");
+    /// ```
+    pub fn pair_order(mut self, order: PairOrder) -> Self {
+        self.options.pair_order = order;
+        self
+    }
+
+    /// Prepends `prefix` to every key, so that several values can share a
+    /// query string without their keys clashing.
+    ///
+    /// ```
+    /// use serde_urlencoded::ser::Serializer;
+    /// use serde::Serialize;
+    ///
+    /// let mut urlencoder = form_urlencoded::Serializer::new(String::new());
+    /// [("name", "rust")]
+    ///     .serialize(Serializer::new(&mut urlencoder).key_prefix("filter_"))
+    ///     .unwrap();
+    /// [("size", 20)]
+    ///     .serialize(Serializer::new(&mut urlencoder).key_prefix("page."))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(urlencoder.finish(), "filter_name=rust&page.size=20");
+    /// ```
+    pub fn key_prefix<P>(mut self, prefix: P) -> Self
+    where
+        P: Into<Cow<'static, str>>,
+    {
+        self.options.key_prefix = prefix.into();
+        self
     }
+
+    /// Sets how `None` values are written.
+    ///
+    /// ```
+    /// use serde_urlencoded::ser::{NoneValue, Serializer};
+    /// use serde::Serialize;
+    ///
+    /// let mut urlencoder = form_urlencoded::Serializer::new(String::new());
+    /// let serializer =
+    ///     Serializer::new(&mut urlencoder).none_value(NoneValue::KeyOnly);
+    /// [("a", Some(1)), ("b", None)].serialize(serializer).unwrap();
+    ///
+    /// assert_eq!(urlencoder.finish(), "a=1&b");
+    /// ```
+    pub fn none_value(mut self, none_value: NoneValue) -> Self {
+        self.options.none_value = none_value;
+        self
+    }
+
+    /// Makes pairs whose value is an empty string be left out, as `None`
+    /// values are by default.
+    pub fn skip_empty_strings(mut self, skip: bool) -> Self {
+        self.options.skip_empty_strings = skip;
+        self
+    }
+
+    /// Makes serialization fail with [`Error::TooLong`] as soon as the
+    /// encoded pairs would be longer than `max_len` bytes, separators
+    /// included.
+    ///
+    /// Only the pairs written by this serializer are counted, encoded as
+    /// told by [`Output::encoding`].
+    ///
+    /// ```
+    /// use serde_urlencoded::ser::{Error, Serializer};
+    /// use serde::Serialize;
+    ///
+    /// let mut urlencoder = form_urlencoded::Serializer::new(String::new());
+    /// let serializer = Serializer::new(&mut urlencoder).max_len(12);
+    /// let result = [("id", 1), ("id", 22), ("id", 333)].serialize(serializer);
+    ///
+    /// assert!(matches!(result, Err(Error::TooLong { ref key, .. }) if key == "id"));
+    /// ```
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.options.max_len = Some(max_len);
+        self
+    }
+}
+
+/// How `None` values are written.
+///
+/// This applies to the values of structs, maps and pair sequences alike.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NoneValue {
+    /// The pair is left out.
+    #[default]
+    Skip,
+    /// The key is written with an empty value: `key=`.
+    Empty,
+    /// The key is written alone: `key`.
+    KeyOnly,
+}
+
+/// The order in which pairs are written.
+///
+/// Sorting compares keys and values before encoding, byte by byte, and
+/// buffers all the pairs of the value until it has been fully serialized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PairOrder {
+    /// The order in which the value serializes them, which for a
+    /// `HashMap` is unspecified.
+    #[default]
+    Insertion,
+    /// Sorted by key, keeping pairs with the same key in insertion order.
+    ByKey,
+    /// Sorted by key, then by value.
+    ByKeyThenValue,
+}
+
+/// How floating point numbers are formatted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// The shortest representation that round-trips, which may use an
+    /// exponent: `1.0`, `0.25`, `1e20`.
+    #[default]
+    Shortest,
+    /// Shortest round-trip digits in plain decimal notation, never using
+    /// an exponent: `1.0`, `0.25`, `100000000000000000000.0`.
+    Plain,
+    /// Like `Plain`, but whole numbers are written without a trailing `.0`:
+    /// `1`, `0.25`, `100000000000000000000`.
+    Minimal,
+    /// Plain decimal notation with exactly that many digits after the
+    /// decimal point, rounding if needed: `Fixed(2)` gives `1.00`, `0.25`,
+    /// `100000000000000000000.00`.
+    Fixed(usize),
+}
+
+#[derive(Clone, Debug, Default)]
+struct Options {
+    key_prefix: Cow<'static, str>,
+    pair_order: PairOrder,
+    max_len: Option<usize>,
+    none_value: NoneValue,
+    skip_empty_strings: bool,
+    float_format: FloatFormat,
+    reject_non_finite: bool,
 }
 
 /// Errors returned during serializing to `application/x-www-form-urlencoded`.
-#[derive(Clone, Debug, PartialEq, Eq)]
+///
+/// `Io` errors compare equal when their kinds are the same.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum Error {
     Custom(Cow<'static, str>),
     Utf8(str::Utf8Error),
+    /// A value of a kind that cannot be serialized where it was found.
+    Unsupported {
+        /// The kind of the rejected value.
+        kind: Kind,
+        /// Where the value was found.
+        location: Location,
+        /// The key of the pair the value belongs to, if known.
+        key: Option<String>,
+    },
+    /// A `NaN` or infinite float, rejected by
+    /// [`Serializer::reject_non_finite`].
+    NonFinite {
+        /// Where the float was found.
+        location: Location,
+        /// The key of the pair the float belongs to, if known.
+        key: Option<String>,
+    },
+    /// The output would have been longer than allowed.
+    TooLong {
+        /// The key of the pair that went over the maximum length.
+        key: String,
+        /// The maximum length, in bytes.
+        max_len: usize,
+    },
+    /// A key was already appended to a `QueryBuilder`.
+    KeyCollision {
+        /// The colliding key.
+        key: String,
+    },
+    /// Writing to an `io::Write` failed.
+    #[cfg(feature = "std")]
+    Io(Arc<io::Error>),
+    /// Writing to a `fmt::Write` failed.
+    Fmt(fmt::Error),
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Error::Custom(a), Error::Custom(b)) => a == b,
+            (Error::Utf8(a), Error::Utf8(b)) => a == b,
+            (
+                Error::Unsupported {
+                    kind,
+                    location,
+                    key,
+                },
+                Error::Unsupported {
+                    kind: other_kind,
+                    location: other_location,
+                    key: other_key,
+                },
+            ) => {
+                kind == other_kind
+                    && location == other_location
+                    && key == other_key
+            }
+            (
+                Error::NonFinite { location, key },
+                Error::NonFinite {
+                    location: other_location,
+                    key: other_key,
+                },
+            ) => location == other_location && key == other_key,
+            (
+                Error::TooLong { key, max_len },
+                Error::TooLong {
+                    key: other_key,
+                    max_len: other_max_len,
+                },
+            ) => key == other_key && max_len == other_max_len,
+            (
+                Error::KeyCollision { key },
+                Error::KeyCollision { key: other_key },
+            ) => key == other_key,
+            #[cfg(feature = "std")]
+            (Error::Io(a), Error::Io(b)) => a.kind() == b.kind(),
+            (Error::Fmt(a), Error::Fmt(b)) => a == b,
+            _ => false,
+        }
+    }
 }
 
+impl Eq for Error {}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             Error::Custom(ref msg) => msg.fmt(f),
             Error::Utf8(ref err) => write!(f, "invalid UTF-8: {}", err),
+            Error::Unsupported {
+                kind,
+                location,
+                ref key,
+            } => {
+                write!(f, "unsupported {} {}", kind, location)?;
+                match *key {
+                    Some(ref key) => write!(f, " for key `{}`", key),
+                    None => Ok(()),
+                }
+            }
+            Error::NonFinite { location, ref key } => {
+                write!(f, "non-finite float {}", location)?;
+                match *key {
+                    Some(ref key) => write!(f, " for key `{}`", key),
+                    None => Ok(()),
+                }
+            }
+            Error::TooLong { ref key, max_len } => write!(
+                f,
+                "output longer than {} bytes at key `{}`",
+                max_len, key
+            ),
+            Error::KeyCollision { ref key } => {
+                write!(f, "key `{}` was already appended", key)
+            }
+            #[cfg(feature = "std")]
+            Error::Io(ref err) => write!(f, "could not write output: {}", err),
+            Error::Fmt(ref err) => write!(f, "could not write output: {}", err),
         }
     }
 }
 
-impl error::Error for Error {
-    fn description(&self) -> &str {
+impl ser::StdError for Error {
+    /// The lower-level source of this error, in the case of a `Utf8`, `Io`
+    /// or `Fmt` error.
+    fn source(&self) -> Option<&(dyn ser::StdError + 'static)> {
         match *self {
-            Error::Custom(ref msg) => msg,
-            Error::Utf8(ref err) => error::Error::description(err),
+            Error::Custom(_)
+            | Error::Unsupported { .. }
+            | Error::NonFinite { .. }
+            | Error::TooLong { .. }
+            | Error::KeyCollision { .. } => None,
+            Error::Utf8(ref err) => Some(err),
+            #[cfg(feature = "std")]
+            Error::Io(ref err) => Some(&**err),
+            Error::Fmt(ref err) => Some(err),
         }
     }
+}
 
-    /// The lower-level cause of this error, in the case of a `Utf8` error.
-    fn cause(&self) -> Option<&dyn error::Error> {
-        match *self {
-            Error::Custom(_) => None,
-            Error::Utf8(ref err) => Some(err),
-        }
+/// A kind of value of the serde data model.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Kind {
+    Bool,
+    Integer,
+    Float,
+    Char,
+    Str,
+    Bytes,
+    Option,
+    Unit,
+    UnitStruct,
+    UnitVariant,
+    NewtypeStruct,
+    NewtypeVariant,
+    Seq,
+    Tuple,
+    TupleStruct,
+    TupleVariant,
+    Map,
+    Struct,
+    StructVariant,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Kind::Bool => "boolean",
+            Kind::Integer => "integer",
+            Kind::Float => "float",
+            Kind::Char => "char",
+            Kind::Str => "string",
+            Kind::Bytes => "bytes",
+            Kind::Option => "option",
+            Kind::Unit => "unit",
+            Kind::UnitStruct => "unit struct",
+            Kind::UnitVariant => "unit variant",
+            Kind::NewtypeStruct => "newtype struct",
+            Kind::NewtypeVariant => "newtype variant",
+            Kind::Seq => "sequence",
+            Kind::Tuple => "tuple",
+            Kind::TupleStruct => "tuple struct",
+            Kind::TupleVariant => "tuple variant",
+            Kind::Map => "map",
+            Kind::Struct => "struct",
+            Kind::StructVariant => "struct variant",
+        })
     }
+}
 
-    /// The lower-level source of this error, in the case of a `Utf8` error.
-    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        match *self {
-            Error::Custom(_) => None,
-            Error::Utf8(ref err) => Some(err),
-        }
+/// Where an unsupported value or a non-finite float was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Location {
+    /// The value being serialized, which must be a map, a struct or a
+    /// sequence of pairs.
+    TopLevel,
+    /// An element of a sequence, which must be a pair.
+    Pair,
+    /// The key of a pair.
+    Key,
+    /// The value of a pair.
+    Value,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match *self {
+            Location::TopLevel => "at top level",
+            Location::Pair => "as pair",
+            Location::Key => "as key",
+            Location::Value => "as value",
+        })
     }
 }
 
@@ -104,140 +882,225 @@ impl ser::Error for Error {
     }
 }
 
+/// The output of a compound serializer, prefixing keys, holding pairs back
+/// until the end when they need sorting, and keeping track of the output
+/// length.
+struct OrderedOutput<'output, O> {
+    output: &'output mut O,
+    prefix: Cow<'static, str>,
+    key: part::PartBuffer,
+    order: PairOrder,
+    buffer: Vec<(String, Option<String>)>,
+    max_len: Option<usize>,
+    len: usize,
+}
+
+impl<'output, O: Output> OrderedOutput<'output, O> {
+    fn new(output: &'output mut O, options: &Options, pairs: usize) -> Self {
+        output.reserve(pairs);
+        OrderedOutput {
+            output,
+            prefix: options.key_prefix.clone(),
+            key: part::PartBuffer::new(),
+            order: options.pair_order,
+            buffer: Vec::new(),
+            max_len: options.max_len,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, key: &str, value: Option<&str>) -> Result<(), Error> {
+        let key = if self.prefix.is_empty() {
+            key
+        } else {
+            self.key.clear();
+            self.key.push_str(&self.prefix);
+            self.key.push_str(key);
+            self.key.as_str()
+        };
+        if let Some(max_len) = self.max_len {
+            let encoding = self.output.encoding();
+            self.len +=
+                usize::from(self.len > 0) + encoding.pair_len(key, value);
+            if self.len > max_len {
+                return Err(Error::too_long(key, max_len));
+            }
+        }
+        match (self.order, value) {
+            (PairOrder::Insertion, Some(value)) => {
+                self.output.append_pair(key, value)
+            }
+            (PairOrder::Insertion, None) => self.output.append_key_only(key),
+            (_, value) => {
+                self.buffer.push((key.into(), value.map(String::from)));
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(mut self) -> Result<&'output mut O, Error> {
+        match self.order {
+            PairOrder::Insertion => {}
+            PairOrder::ByKey => self.buffer.sort_by(|a, b| a.0.cmp(&b.0)),
+            PairOrder::ByKeyThenValue => self.buffer.sort(),
+        }
+        for (key, value) in &self.buffer {
+            match value {
+                Some(value) => self.output.append_pair(key, value)?,
+                None => self.output.append_key_only(key)?,
+            }
+        }
+        Ok(self.output)
+    }
+}
+
+impl<'output, O: Output> Output for OrderedOutput<'output, O> {
+    fn append_pair(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        self.push(key, Some(value))
+    }
+
+    fn append_key_only(&mut self, key: &str) -> Result<(), Error> {
+        self.push(key, None)
+    }
+}
+
 /// Sequence serializer.
-pub struct SeqSerializer<'input, 'output, Target: UrlEncodedTarget> {
-    urlencoder: &'output mut UrlEncodedSerializer<'input, Target>,
+pub struct SeqSerializer<'output, O: Output> {
+    output: OrderedOutput<'output, O>,
+    options: Options,
+    key: key::KeyBuffer,
 }
 
 /// Tuple serializer.
 ///
 /// Mostly used for arrays.
-pub struct TupleSerializer<'input, 'output, Target: UrlEncodedTarget> {
-    urlencoder: &'output mut UrlEncodedSerializer<'input, Target>,
+pub struct TupleSerializer<'output, O: Output> {
+    output: OrderedOutput<'output, O>,
+    options: Options,
+    key: key::KeyBuffer,
 }
 
 /// Tuple struct serializer.
 ///
 /// Never instantiated, tuple structs are not supported.
-pub struct TupleStructSerializer<'input, 'output, T: UrlEncodedTarget> {
-    inner: ser::Impossible<&'output mut UrlEncodedSerializer<'input, T>, Error>,
+pub struct TupleStructSerializer<'output, O: Output> {
+    inner: ser::Impossible<&'output mut O, Error>,
 }
 
 /// Tuple variant serializer.
 ///
 /// Never instantiated, tuple variants are not supported.
-pub struct TupleVariantSerializer<'input, 'output, T: UrlEncodedTarget> {
-    inner: ser::Impossible<&'output mut UrlEncodedSerializer<'input, T>, Error>,
+pub struct TupleVariantSerializer<'output, O: Output> {
+    inner: ser::Impossible<&'output mut O, Error>,
 }
 
 /// Map serializer.
-pub struct MapSerializer<'input, 'output, Target: UrlEncodedTarget> {
-    urlencoder: &'output mut UrlEncodedSerializer<'input, Target>,
-    key: Option<Cow<'static, str>>,
+pub struct MapSerializer<'output, O: Output> {
+    output: OrderedOutput<'output, O>,
+    options: Options,
+    key: key::KeyBuffer,
 }
 
 /// Struct serializer.
-pub struct StructSerializer<'input, 'output, Target: UrlEncodedTarget> {
-    urlencoder: &'output mut UrlEncodedSerializer<'input, Target>,
+pub struct StructSerializer<'output, O: Output> {
+    output: OrderedOutput<'output, O>,
+    options: Options,
 }
 
 /// Struct variant serializer.
 ///
 /// Never instantiated, struct variants are not supported.
-pub struct StructVariantSerializer<'input, 'output, T: UrlEncodedTarget> {
-    inner: ser::Impossible<&'output mut UrlEncodedSerializer<'input, T>, Error>,
+pub struct StructVariantSerializer<'output, O: Output> {
+    inner: ser::Impossible<&'output mut O, Error>,
 }
 
-impl<'input, 'output, Target> ser::Serializer
-    for Serializer<'input, 'output, Target>
+impl<'output, O> ser::Serializer for Serializer<'output, O>
 where
-    Target: 'output + UrlEncodedTarget,
+    O: 'output + Output,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut O;
     type Error = Error;
-    type SerializeSeq = SeqSerializer<'input, 'output, Target>;
-    type SerializeTuple = TupleSerializer<'input, 'output, Target>;
-    type SerializeTupleStruct = TupleStructSerializer<'input, 'output, Target>;
-    type SerializeTupleVariant =
-        TupleVariantSerializer<'input, 'output, Target>;
-    type SerializeMap = MapSerializer<'input, 'output, Target>;
-    type SerializeStruct = StructSerializer<'input, 'output, Target>;
-    type SerializeStructVariant =
-        StructVariantSerializer<'input, 'output, Target>;
+    type SerializeSeq = SeqSerializer<'output, O>;
+    type SerializeTuple = TupleSerializer<'output, O>;
+    type SerializeTupleStruct = TupleStructSerializer<'output, O>;
+    type SerializeTupleVariant = TupleVariantSerializer<'output, O>;
+    type SerializeMap = MapSerializer<'output, O>;
+    type SerializeStruct = StructSerializer<'output, O>;
+    type SerializeStructVariant = StructVariantSerializer<'output, O>;
 
     /// Returns an error.
     fn serialize_bool(self, _v: bool) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Bool))
     }
 
     /// Returns an error.
     fn serialize_i8(self, _v: i8) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Integer))
     }
 
     /// Returns an error.
     fn serialize_i16(self, _v: i16) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Integer))
     }
 
     /// Returns an error.
     fn serialize_i32(self, _v: i32) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Integer))
     }
 
     /// Returns an error.
     fn serialize_i64(self, _v: i64) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Integer))
     }
 
     /// Returns an error.
     fn serialize_u8(self, _v: u8) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Integer))
     }
 
     /// Returns an error.
     fn serialize_u16(self, _v: u16) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Integer))
     }
 
     /// Returns an error.
     fn serialize_u32(self, _v: u32) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Integer))
     }
 
     /// Returns an error.
     fn serialize_u64(self, _v: u64) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Integer))
     }
 
     /// Returns an error.
     fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Float))
     }
 
     /// Returns an error.
     fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Float))
     }
 
     /// Returns an error.
     fn serialize_char(self, _v: char) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Char))
     }
 
     /// Returns an error.
     fn serialize_str(self, _value: &str) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Str))
     }
 
     /// Returns an error.
     fn serialize_bytes(self, _value: &[u8]) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::Bytes))
     }
 
     /// Returns `Ok`.
     fn serialize_unit(self) -> Result<Self::Ok, Error> {
-        Ok(self.urlencoder)
+        Ok(self.output)
     }
 
     /// Returns `Ok`.
@@ -245,7 +1108,7 @@ where
         self,
         _name: &'static str,
     ) -> Result<Self::Ok, Error> {
-        Ok(self.urlencoder)
+        Ok(self.output)
     }
 
     /// Returns an error.
@@ -255,7 +1118,7 @@ where
         _variant_index: u32,
         _variant: &'static str,
     ) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::UnitVariant))
     }
 
     /// Serializes the inner value, ignoring the newtype name.
@@ -275,12 +1138,12 @@ where
         _variant: &'static str,
         _value: &T,
     ) -> Result<Self::Ok, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::NewtypeVariant))
     }
 
     /// Returns `Ok`.
     fn serialize_none(self) -> Result<Self::Ok, Error> {
-        Ok(self.urlencoder)
+        Ok(self.output)
     }
 
     /// Serializes the given value.
@@ -291,23 +1154,30 @@ where
         value.serialize(self)
     }
 
-    /// Serialize a sequence, given length (if any) is ignored.
+    /// Serialize a sequence, given length (if any) is used to reserve
+    /// space in the output.
     fn serialize_seq(
         self,
-        _len: Option<usize>,
+        len: Option<usize>,
     ) -> Result<Self::SerializeSeq, Error> {
+        let pairs = len.unwrap_or(0);
         Ok(SeqSerializer {
-            urlencoder: self.urlencoder,
+            output: OrderedOutput::new(self.output, &self.options, pairs),
+            options: self.options,
+            key: key::KeyBuffer::new(),
         })
     }
 
-    /// Returns an error.
+    /// Serializes a tuple, given length is used to reserve space in the
+    /// output.
     fn serialize_tuple(
         self,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTuple, Error> {
         Ok(TupleSerializer {
-            urlencoder: self.urlencoder,
+            output: OrderedOutput::new(self.output, &self.options, len),
+            options: self.options,
+            key: key::KeyBuffer::new(),
         })
     }
 
@@ -317,7 +1187,7 @@ where
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::TupleStruct))
     }
 
     /// Returns an error.
@@ -328,28 +1198,33 @@ where
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::TupleVariant))
     }
 
-    /// Serializes a map, given length is ignored.
+    /// Serializes a map, given length (if any) is used to reserve space in
+    /// the output.
     fn serialize_map(
         self,
-        _len: Option<usize>,
+        len: Option<usize>,
     ) -> Result<Self::SerializeMap, Error> {
+        let pairs = len.unwrap_or(0);
         Ok(MapSerializer {
-            urlencoder: self.urlencoder,
-            key: None,
+            output: OrderedOutput::new(self.output, &self.options, pairs),
+            options: self.options,
+            key: key::KeyBuffer::new(),
         })
     }
 
-    /// Serializes a struct, given length is ignored.
+    /// Serializes a struct, given length is used to reserve space in the
+    /// output.
     fn serialize_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStruct, Error> {
         Ok(StructSerializer {
-            urlencoder: self.urlencoder,
+            output: OrderedOutput::new(self.output, &self.options, len),
+            options: self.options,
         })
     }
 
@@ -361,56 +1236,61 @@ where
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant, Error> {
-        Err(Error::top_level())
+        Err(Error::top_level(Kind::StructVariant))
     }
 }
 
-impl<'input, 'output, Target> ser::SerializeSeq
-    for SeqSerializer<'input, 'output, Target>
+impl<'output, O> ser::SerializeSeq for SeqSerializer<'output, O>
 where
-    Target: 'output + UrlEncodedTarget,
+    O: 'output + Output,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut O;
     type Error = Error;
 
     fn serialize_element<T: ?Sized + ser::Serialize>(
         &mut self,
         value: &T,
     ) -> Result<(), Error> {
-        value.serialize(pair::PairSerializer::new(self.urlencoder))
+        value.serialize(pair::PairSerializer::new(
+            &mut self.output,
+            &self.options,
+            &mut self.key,
+        ))
     }
 
     fn end(self) -> Result<Self::Ok, Error> {
-        Ok(self.urlencoder)
+        self.output.finish()
     }
 }
 
-impl<'input, 'output, Target> ser::SerializeTuple
-    for TupleSerializer<'input, 'output, Target>
+impl<'output, O> ser::SerializeTuple for TupleSerializer<'output, O>
 where
-    Target: 'output + UrlEncodedTarget,
+    O: 'output + Output,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut O;
     type Error = Error;
 
     fn serialize_element<T: ?Sized + ser::Serialize>(
         &mut self,
         value: &T,
     ) -> Result<(), Error> {
-        value.serialize(pair::PairSerializer::new(self.urlencoder))
+        value.serialize(pair::PairSerializer::new(
+            &mut self.output,
+            &self.options,
+            &mut self.key,
+        ))
     }
 
     fn end(self) -> Result<Self::Ok, Error> {
-        Ok(self.urlencoder)
+        self.output.finish()
     }
 }
 
-impl<'input, 'output, Target> ser::SerializeTupleStruct
-    for TupleStructSerializer<'input, 'output, Target>
+impl<'output, O> ser::SerializeTupleStruct for TupleStructSerializer<'output, O>
 where
-    Target: 'output + UrlEncodedTarget,
+    O: 'output + Output,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut O;
     type Error = Error;
 
     fn serialize_field<T: ?Sized + ser::Serialize>(
@@ -425,12 +1305,12 @@ where
     }
 }
 
-impl<'input, 'output, Target> ser::SerializeTupleVariant
-    for TupleVariantSerializer<'input, 'output, Target>
+impl<'output, O> ser::SerializeTupleVariant
+    for TupleVariantSerializer<'output, O>
 where
-    Target: 'output + UrlEncodedTarget,
+    O: 'output + Output,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut O;
     type Error = Error;
 
     fn serialize_field<T: ?Sized + ser::Serialize>(
@@ -445,12 +1325,11 @@ where
     }
 }
 
-impl<'input, 'output, Target> ser::SerializeMap
-    for MapSerializer<'input, 'output, Target>
+impl<'output, O> ser::SerializeMap for MapSerializer<'output, O>
 where
-    Target: 'output + UrlEncodedTarget,
+    O: 'output + Output,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut O;
     type Error = Error;
 
     fn serialize_entry<
@@ -461,24 +1340,29 @@ where
         key: &K,
         value: &V,
     ) -> Result<(), Error> {
+        let options = &self.options;
+        let output = &mut self.output;
         let key_sink = key::KeySink::new(|key| {
-            let value_sink = value::ValueSink::new(self.urlencoder, &key);
-            value.serialize(part::PartSerializer::new(value_sink))?;
-            self.key = None;
-            Ok(())
+            let value_sink = value::ValueSink::new(output, &key, options);
+            value.serialize(part::PartSerializer::new(value_sink, options))
         });
-        let entry_serializer = part::PartSerializer::new(key_sink);
-        key.serialize(entry_serializer)
+        let entry_serializer = part::PartSerializer::new(key_sink, options);
+        key.serialize(entry_serializer)?;
+        self.key.clear();
+        Ok(())
     }
 
     fn serialize_key<T: ?Sized + ser::Serialize>(
         &mut self,
         key: &T,
     ) -> Result<(), Error> {
-        let key_sink = key::KeySink::new(|key| Ok(key.into()));
-        let key_serializer = part::PartSerializer::new(key_sink);
-        self.key = Some(key.serialize(key_serializer)?);
-        Ok(())
+        let buffer = &mut self.key;
+        let key_sink = key::KeySink::new(|key| {
+            buffer.set(key);
+            Ok(())
+        });
+        let key_serializer = part::PartSerializer::new(key_sink, &self.options);
+        key.serialize(key_serializer)
     }
 
     fn serialize_value<T: ?Sized + ser::Serialize>(
@@ -486,25 +1370,27 @@ where
         value: &T,
     ) -> Result<(), Error> {
         {
-            let key = self.key.as_ref().ok_or_else(Error::no_key)?;
-            let value_sink = value::ValueSink::new(self.urlencoder, &key);
-            value.serialize(part::PartSerializer::new(value_sink))?;
+            let key = self.key.get().ok_or_else(Error::no_key)?;
+            let value_sink =
+                value::ValueSink::new(&mut self.output, key, &self.options);
+            let value_serializer =
+                part::PartSerializer::new(value_sink, &self.options);
+            value.serialize(value_serializer)?;
         }
-        self.key = None;
+        self.key.clear();
         Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Error> {
-        Ok(self.urlencoder)
+        self.output.finish()
     }
 }
 
-impl<'input, 'output, Target> ser::SerializeStruct
-    for StructSerializer<'input, 'output, Target>
+impl<'output, O> ser::SerializeStruct for StructSerializer<'output, O>
 where
-    Target: 'output + UrlEncodedTarget,
+    O: 'output + Output,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut O;
     type Error = Error;
 
     fn serialize_field<T: ?Sized + ser::Serialize>(
@@ -512,21 +1398,22 @@ where
         key: &'static str,
         value: &T,
     ) -> Result<(), Error> {
-        let value_sink = value::ValueSink::new(self.urlencoder, key);
-        value.serialize(part::PartSerializer::new(value_sink))
+        let value_sink =
+            value::ValueSink::new(&mut self.output, key, &self.options);
+        value.serialize(part::PartSerializer::new(value_sink, &self.options))
     }
 
     fn end(self) -> Result<Self::Ok, Error> {
-        Ok(self.urlencoder)
+        self.output.finish()
     }
 }
 
-impl<'input, 'output, Target> ser::SerializeStructVariant
-    for StructVariantSerializer<'input, 'output, Target>
+impl<'output, O> ser::SerializeStructVariant
+    for StructVariantSerializer<'output, O>
 where
-    Target: 'output + UrlEncodedTarget,
+    O: 'output + Output,
 {
-    type Ok = &'output mut UrlEncodedSerializer<'input, Target>;
+    type Ok = &'output mut O;
     type Error = Error;
 
     fn serialize_field<T: ?Sized + ser::Serialize>(
@@ -543,9 +1430,30 @@ where
 }
 
 impl Error {
-    fn top_level() -> Self {
-        let msg = "top-level serializer supports only maps and structs";
-        Error::Custom(msg.into())
+    fn unsupported(kind: Kind, location: Location, key: Option<&str>) -> Self {
+        Error::Unsupported {
+            kind,
+            location,
+            key: key.map(String::from),
+        }
+    }
+
+    fn non_finite(location: Location, key: Option<&str>) -> Self {
+        Error::NonFinite {
+            location,
+            key: key.map(String::from),
+        }
+    }
+
+    fn too_long(key: &str, max_len: usize) -> Self {
+        Error::TooLong {
+            key: key.into(),
+            max_len,
+        }
+    }
+
+    fn top_level(kind: Kind) -> Self {
+        Error::unsupported(kind, Location::TopLevel, None)
     }
 
     fn no_key() -> Self {
@@ -1,36 +1,38 @@
-use crate::ser::key::KeySink;
+use crate::ser::key::{KeyBuffer, KeySink};
 use crate::ser::part::PartSerializer;
 use crate::ser::value::ValueSink;
-use crate::ser::Error;
-use form_urlencoded::Serializer as UrlEncodedSerializer;
-use form_urlencoded::Target as UrlEncodedTarget;
+use crate::ser::{Error, Kind, Location, Options, Output};
+use core::mem;
 use serde::ser;
-use std::borrow::Cow;
-use std::mem;
 
-pub struct PairSerializer<'input, 'target, Target: UrlEncodedTarget> {
-    urlencoder: &'target mut UrlEncodedSerializer<'input, Target>,
+pub struct PairSerializer<'target, O: Output> {
+    output: &'target mut O,
+    options: &'target Options,
+    key: &'target mut KeyBuffer,
     state: PairState,
 }
 
-impl<'input, 'target, Target> PairSerializer<'input, 'target, Target>
+impl<'target, O> PairSerializer<'target, O>
 where
-    Target: 'target + UrlEncodedTarget,
+    O: 'target + Output,
 {
     pub fn new(
-        urlencoder: &'target mut UrlEncodedSerializer<'input, Target>,
+        output: &'target mut O,
+        options: &'target Options,
+        key: &'target mut KeyBuffer,
     ) -> Self {
         PairSerializer {
-            urlencoder,
+            output,
+            options,
+            key,
             state: PairState::WaitingForKey,
         }
     }
 }
 
-impl<'input, 'target, Target> ser::Serializer
-    for PairSerializer<'input, 'target, Target>
+impl<'target, O> ser::Serializer for PairSerializer<'target, O>
 where
-    Target: 'target + UrlEncodedTarget,
+    O: 'target + Output,
 {
     type Ok = ();
     type Error = Error;
@@ -43,67 +45,67 @@ where
     type SerializeStructVariant = ser::Impossible<(), Error>;
 
     fn serialize_bool(self, _v: bool) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Bool))
     }
 
     fn serialize_i8(self, _v: i8) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Integer))
     }
 
     fn serialize_i16(self, _v: i16) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Integer))
     }
 
     fn serialize_i32(self, _v: i32) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Integer))
     }
 
     fn serialize_i64(self, _v: i64) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Integer))
     }
 
     fn serialize_u8(self, _v: u8) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Integer))
     }
 
     fn serialize_u16(self, _v: u16) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Integer))
     }
 
     fn serialize_u32(self, _v: u32) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Integer))
     }
 
     fn serialize_u64(self, _v: u64) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Integer))
     }
 
     fn serialize_f32(self, _v: f32) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Float))
     }
 
     fn serialize_f64(self, _v: f64) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Float))
     }
 
     fn serialize_char(self, _v: char) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Char))
     }
 
     fn serialize_str(self, _value: &str) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Str))
     }
 
     fn serialize_bytes(self, _value: &[u8]) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Bytes))
     }
 
     fn serialize_unit(self) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Unit))
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::UnitStruct))
     }
 
     fn serialize_unit_variant(
@@ -112,7 +114,7 @@ where
         _variant_index: u32,
         _variant: &'static str,
     ) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::UnitVariant))
     }
 
     fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
@@ -130,7 +132,7 @@ where
         _variant: &'static str,
         _value: &T,
     ) -> Result<(), Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::NewtypeVariant))
     }
 
     fn serialize_none(self) -> Result<(), Error> {
@@ -148,14 +150,14 @@ where
         self,
         _len: Option<usize>,
     ) -> Result<Self::SerializeSeq, Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Seq))
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self, Error> {
         if len == 2 {
             Ok(self)
         } else {
-            Err(Error::unsupported_pair())
+            Err(Error::unsupported_pair(Kind::Tuple))
         }
     }
 
@@ -164,7 +166,7 @@ where
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleStruct, Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::TupleStruct))
     }
 
     fn serialize_tuple_variant(
@@ -174,14 +176,14 @@ where
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant, Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::TupleVariant))
     }
 
     fn serialize_map(
         self,
         _len: Option<usize>,
     ) -> Result<Self::SerializeMap, Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Map))
     }
 
     fn serialize_struct(
@@ -189,7 +191,7 @@ where
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::Struct))
     }
 
     fn serialize_struct_variant(
@@ -199,14 +201,13 @@ where
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant, Error> {
-        Err(Error::unsupported_pair())
+        Err(Error::unsupported_pair(Kind::StructVariant))
     }
 }
 
-impl<'input, 'target, Target> ser::SerializeTuple
-    for PairSerializer<'input, 'target, Target>
+impl<'target, O> ser::SerializeTuple for PairSerializer<'target, O>
 where
-    Target: 'target + UrlEncodedTarget,
+    O: 'target + Output,
 {
     type Ok = ();
     type Error = Error;
@@ -217,23 +218,30 @@ where
     ) -> Result<(), Error> {
         match mem::replace(&mut self.state, PairState::Done) {
             PairState::WaitingForKey => {
-                let key_sink = KeySink::new(|key| Ok(key.into()));
-                let key_serializer = PartSerializer::new(key_sink);
-                self.state = PairState::WaitingForValue {
-                    key: value.serialize(key_serializer)?,
-                };
+                let buffer = &mut *self.key;
+                let key_sink = KeySink::new(|key| {
+                    buffer.set(key);
+                    Ok(())
+                });
+                let key_serializer =
+                    PartSerializer::new(key_sink, self.options);
+                value.serialize(key_serializer)?;
+                self.state = PairState::WaitingForValue;
                 Ok(())
             }
-            PairState::WaitingForValue { key } => {
+            PairState::WaitingForValue => {
                 let result = {
-                    let value_sink = ValueSink::new(self.urlencoder, &key);
-                    let value_serializer = PartSerializer::new(value_sink);
+                    let key = self.key.get().ok_or_else(Error::no_key)?;
+                    let value_sink =
+                        ValueSink::new(self.output, key, self.options);
+                    let value_serializer =
+                        PartSerializer::new(value_sink, self.options);
                     value.serialize(value_serializer)
                 };
                 if result.is_ok() {
                     self.state = PairState::Done;
                 } else {
-                    self.state = PairState::WaitingForValue { key };
+                    self.state = PairState::WaitingForValue;
                 }
                 result
             }
@@ -252,7 +260,7 @@ where
 
 enum PairState {
     WaitingForKey,
-    WaitingForValue { key: Cow<'static, str> },
+    WaitingForValue,
     Done,
 }
 
@@ -265,7 +273,7 @@ impl Error {
         Error::Custom("this pair has not yet been serialized".into())
     }
 
-    fn unsupported_pair() -> Self {
-        Error::Custom("unsupported pair".into())
+    fn unsupported_pair(kind: Kind) -> Self {
+        Error::unsupported(kind, Location::Pair, None)
     }
 }
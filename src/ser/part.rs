@@ -1,14 +1,17 @@
-use crate::ser::Error;
+use crate::ser::{Error, FloatFormat, Kind, Options};
+use alloc::string::String;
+use core::fmt::{self, Write};
+use core::str;
 use serde::ser;
-use std::str;
 
-pub struct PartSerializer<S> {
+pub struct PartSerializer<'options, S> {
     sink: S,
+    options: &'options Options,
 }
 
-impl<S: Sink> PartSerializer<S> {
-    pub fn new(sink: S) -> Self {
-        PartSerializer { sink }
+impl<'options, S: Sink> PartSerializer<'options, S> {
+    pub fn new(sink: S, options: &'options Options) -> Self {
+        PartSerializer { sink, options }
     }
 }
 
@@ -21,7 +24,6 @@ pub trait Sink: Sized {
     ) -> Result<Self::Ok, Error>;
 
     fn serialize_str(self, value: &str) -> Result<Self::Ok, Error>;
-    fn serialize_string(self, value: String) -> Result<Self::Ok, Error>;
     fn serialize_none(self) -> Result<Self::Ok, Error>;
 
     fn serialize_some<T: ?Sized + ser::Serialize>(
@@ -29,10 +31,11 @@ pub trait Sink: Sized {
         value: &T,
     ) -> Result<Self::Ok, Error>;
 
-    fn unsupported(self) -> Error;
+    fn unsupported(self, kind: Kind) -> Error;
+    fn non_finite(self) -> Error;
 }
 
-impl<S: Sink> ser::Serializer for PartSerializer<S> {
+impl<'options, S: Sink> ser::Serializer for PartSerializer<'options, S> {
     type Ok = S::Ok;
     type Error = Error;
     type SerializeSeq = ser::Impossible<S::Ok, Error>;
@@ -97,13 +100,23 @@ impl<S: Sink> ser::Serializer for PartSerializer<S> {
     }
 
     fn serialize_char(self, v: char) -> Result<S::Ok, Error> {
-        self.sink.serialize_string(v.to_string())
+        let mut buf = [0; 4];
+        ser::Serializer::serialize_str(self, v.encode_utf8(&mut buf))
     }
 
     fn serialize_str(self, value: &str) -> Result<S::Ok, Error> {
         self.sink.serialize_str(value)
     }
 
+    fn collect_str<T: ?Sized + fmt::Display>(
+        self,
+        value: &T,
+    ) -> Result<S::Ok, Error> {
+        let mut buf = PartBuffer::new();
+        write!(buf, "{}", value).map_err(Error::Fmt)?;
+        ser::Serializer::serialize_str(self, buf.as_str())
+    }
+
     fn serialize_bytes(self, value: &[u8]) -> Result<S::Ok, Error> {
         match str::from_utf8(value) {
             Ok(value) => self.sink.serialize_str(value),
@@ -112,7 +125,7 @@ impl<S: Sink> ser::Serializer for PartSerializer<S> {
     }
 
     fn serialize_unit(self) -> Result<S::Ok, Error> {
-        Err(self.sink.unsupported())
+        Err(self.sink.unsupported(Kind::Unit))
     }
 
     fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, Error> {
@@ -143,7 +156,7 @@ impl<S: Sink> ser::Serializer for PartSerializer<S> {
         _variant: &'static str,
         _value: &T,
     ) -> Result<S::Ok, Error> {
-        Err(self.sink.unsupported())
+        Err(self.sink.unsupported(Kind::NewtypeVariant))
     }
 
     fn serialize_none(self) -> Result<S::Ok, Error> {
@@ -161,14 +174,14 @@ impl<S: Sink> ser::Serializer for PartSerializer<S> {
         self,
         _len: Option<usize>,
     ) -> Result<Self::SerializeSeq, Error> {
-        Err(self.sink.unsupported())
+        Err(self.sink.unsupported(Kind::Seq))
     }
 
     fn serialize_tuple(
         self,
         _len: usize,
     ) -> Result<Self::SerializeTuple, Error> {
-        Err(self.sink.unsupported())
+        Err(self.sink.unsupported(Kind::Tuple))
     }
 
     fn serialize_tuple_struct(
@@ -176,7 +189,7 @@ impl<S: Sink> ser::Serializer for PartSerializer<S> {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTuple, Error> {
-        Err(self.sink.unsupported())
+        Err(self.sink.unsupported(Kind::TupleStruct))
     }
 
     fn serialize_tuple_variant(
@@ -186,14 +199,14 @@ impl<S: Sink> ser::Serializer for PartSerializer<S> {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant, Error> {
-        Err(self.sink.unsupported())
+        Err(self.sink.unsupported(Kind::TupleVariant))
     }
 
     fn serialize_map(
         self,
         _len: Option<usize>,
     ) -> Result<Self::SerializeMap, Error> {
-        Err(self.sink.unsupported())
+        Err(self.sink.unsupported(Kind::Map))
     }
 
     fn serialize_struct(
@@ -201,7 +214,7 @@ impl<S: Sink> ser::Serializer for PartSerializer<S> {
         _name: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStruct, Error> {
-        Err(self.sink.unsupported())
+        Err(self.sink.unsupported(Kind::Struct))
     }
 
     fn serialize_struct_variant(
@@ -211,11 +224,11 @@ impl<S: Sink> ser::Serializer for PartSerializer<S> {
         _variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant, Error> {
-        Err(self.sink.unsupported())
+        Err(self.sink.unsupported(Kind::StructVariant))
     }
 }
 
-impl<S: Sink> PartSerializer<S> {
+impl<'options, S: Sink> PartSerializer<'options, S> {
     fn serialize_integer<I>(self, value: I) -> Result<S::Ok, Error>
     where
         I: itoa::Integer,
@@ -227,10 +240,105 @@ impl<S: Sink> PartSerializer<S> {
 
     fn serialize_floating<F>(self, value: F) -> Result<S::Ok, Error>
     where
-        F: ryu::Float,
+        F: Floating,
     {
-        let mut buf = ryu::Buffer::new();
-        let part = buf.format(value);
-        ser::Serializer::serialize_str(self, part)
+        if self.options.reject_non_finite && !value.is_finite() {
+            return Err(self.sink.non_finite());
+        }
+        match self.options.float_format {
+            FloatFormat::Shortest => {
+                let mut buf = ryu::Buffer::new();
+                let part = buf.format(value);
+                ser::Serializer::serialize_str(self, part)
+            }
+            FloatFormat::Plain => {
+                // `Display` never uses an exponent but drops the fractional
+                // part of whole numbers.
+                let mut buf = PartBuffer::new();
+                write!(buf, "{}", value).map_err(Error::Fmt)?;
+                if value.is_finite() && !buf.as_str().contains('.') {
+                    buf.push_str(".0");
+                }
+                ser::Serializer::serialize_str(self, buf.as_str())
+            }
+            FloatFormat::Minimal => ser::Serializer::collect_str(self, &value),
+            FloatFormat::Fixed(precision) => {
+                let mut buf = PartBuffer::new();
+                write!(buf, "{:.*}", precision, value).map_err(Error::Fmt)?;
+                ser::Serializer::serialize_str(self, buf.as_str())
+            }
+        }
+    }
+}
+
+/// A buffer keeping a part on the stack, only allocating for parts longer
+/// than a few dozen bytes.
+pub struct PartBuffer {
+    bytes: [u8; 64],
+    len: usize,
+    spilled: Option<String>,
+}
+
+impl PartBuffer {
+    pub fn new() -> Self {
+        PartBuffer {
+            bytes: [0; 64],
+            len: 0,
+            spilled: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        if let Some(spilled) = &mut self.spilled {
+            spilled.clear();
+        }
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        if let Some(spilled) = &mut self.spilled {
+            spilled.push_str(s);
+        } else if let Some(dest) =
+            self.bytes.get_mut(self.len..self.len + s.len())
+        {
+            dest.copy_from_slice(s.as_bytes());
+            self.len += s.len();
+        } else {
+            let mut spilled = String::with_capacity(self.len + s.len());
+            spilled.push_str(self.as_str());
+            spilled.push_str(s);
+            self.spilled = Some(spilled);
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match &self.spilled {
+            Some(spilled) => spilled,
+            // Only whole strings are ever copied into the bytes.
+            None => str::from_utf8(&self.bytes[..self.len]).unwrap(),
+        }
+    }
+}
+
+impl fmt::Write for PartBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+pub trait Floating: ryu::Float + fmt::Display {
+    fn is_finite(self) -> bool;
+}
+
+impl Floating for f32 {
+    fn is_finite(self) -> bool {
+        f32::is_finite(self)
+    }
+}
+
+impl Floating for f64 {
+    fn is_finite(self) -> bool {
+        f64::is_finite(self)
     }
 }
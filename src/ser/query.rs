@@ -0,0 +1,204 @@
+#[cfg(feature = "std")]
+use crate::ser::encoder::{Encoder, IoWriter};
+use crate::ser::{Error, Output, Serializer};
+use alloc::string::String;
+use alloc::vec::Vec;
+use form_urlencoded::Serializer as UrlEncodedSerializer;
+use form_urlencoded::Target as UrlEncodedTarget;
+use serde::ser::Serialize;
+#[cfg(feature = "std")]
+use std::io;
+
+/// Combines the pairs of several values and individual pairs into a single
+/// query string.
+///
+/// Each call to [`append`] or [`pair`] is checked for key collisions with
+/// the pairs of the previous calls, as told by [`collisions`]. As an
+/// `Output`, it also accepts the pairs of a configured [`Serializer`],
+/// which are checked together when the next call is made.
+///
+/// ```
+/// use serde::Serialize;
+/// use serde_urlencoded::ser::{Collisions, QueryBuilder, Serializer};
+///
+/// #[derive(serde_derive::Serialize)]
+/// struct Page {
+///     page: u32,
+///     size: u32,
+/// }
+///
+/// let mut builder = QueryBuilder::new().collisions(Collisions::Replace);
+/// builder.append(Page { page: 1, size: 20 })?;
+/// [("name", "rust")]
+///     .serialize(Serializer::new(&mut builder).key_prefix("filter_"))?;
+/// builder.pair("page", "2")?;
+///
+/// assert_eq!(builder.finish(), "size=20&filter_name=rust&page=2");
+/// # Ok::<(), serde_urlencoded::ser::Error>(())
+/// ```
+///
+/// [`append`]: QueryBuilder::append
+/// [`pair`]: QueryBuilder::pair
+/// [`collisions`]: QueryBuilder::collisions
+#[derive(Clone, Debug, Default)]
+pub struct QueryBuilder {
+    pairs: Vec<(String, Option<String>)>,
+    /// The number of pairs already checked for collisions.
+    checked: usize,
+    collisions: Collisions,
+}
+
+/// How a `QueryBuilder` handles a key that was already appended by a
+/// previous call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Collisions {
+    /// Both pairs are kept, in order.
+    #[default]
+    Append,
+    /// The previous pairs with the same key are removed.
+    Replace,
+    /// The call fails with [`Error::KeyCollision`].
+    Error,
+}
+
+impl QueryBuilder {
+    /// Returns a new, empty `QueryBuilder` appending colliding keys.
+    pub fn new() -> Self {
+        QueryBuilder::default()
+    }
+
+    /// Sets how key collisions are handled.
+    pub fn collisions(mut self, collisions: Collisions) -> Self {
+        self.collisions = collisions;
+        self
+    }
+
+    /// Appends the pairs of a value.
+    ///
+    /// Nothing is appended if serialization fails.
+    pub fn append<T: Serialize>(
+        &mut self,
+        input: T,
+    ) -> Result<&mut Self, Error> {
+        self.check();
+        let result = input.serialize(Serializer::new(&mut *self));
+        if let Err(err) = result {
+            self.pairs.truncate(self.checked);
+            return Err(err);
+        }
+        self.check();
+        Ok(self)
+    }
+
+    /// Appends a single unencoded pair.
+    pub fn pair(&mut self, key: &str, value: &str) -> Result<&mut Self, Error> {
+        self.check();
+        self.push(key, Some(value))?;
+        self.check();
+        Ok(self)
+    }
+
+    /// Appends the pairs to a `form_urlencoded::Serializer`.
+    pub fn finish_into<T: UrlEncodedTarget>(
+        mut self,
+        urlencoder: &mut UrlEncodedSerializer<'_, T>,
+    ) {
+        self.check();
+        for (key, value) in &self.pairs {
+            match value {
+                Some(value) => urlencoder.append_pair(key, value),
+                None => urlencoder.append_key_only(key),
+            };
+        }
+    }
+
+    /// Returns the query string.
+    pub fn finish(self) -> String {
+        let mut urlencoder = UrlEncodedSerializer::new(String::new());
+        self.finish_into(&mut urlencoder);
+        urlencoder.finish()
+    }
+
+    /// Replaces the query of a `Url`, which is removed if there are no
+    /// pairs.
+    ///
+    /// ```
+    /// use serde_urlencoded::ser::QueryBuilder;
+    ///
+    /// let mut url = url::Url::parse("https://example.com/?old=1#top").unwrap();
+    /// let mut builder = QueryBuilder::new();
+    /// builder.pair("q", "comté")?;
+    /// builder.finish_url(&mut url);
+    ///
+    /// assert_eq!(url.as_str(), "https://example.com/?q=comt%C3%A9#top");
+    /// # Ok::<(), serde_urlencoded::ser::Error>(())
+    /// ```
+    #[cfg(feature = "url")]
+    pub fn finish_url(self, url: &mut url::Url) {
+        if self.pairs.is_empty() {
+            url.set_query(None);
+        } else {
+            let mut urlencoder = url.query_pairs_mut();
+            urlencoder.clear();
+            self.finish_into(&mut urlencoder);
+        }
+    }
+
+    /// Writes the query string into an `io::Write`.
+    #[cfg(feature = "std")]
+    pub fn finish_writer<W: io::Write>(
+        mut self,
+        writer: W,
+    ) -> Result<(), Error> {
+        self.check();
+        let mut encoder = Encoder::new(IoWriter {
+            inner: writer,
+            error: None,
+        });
+        let result =
+            self.pairs.iter().try_for_each(|(key, value)| match value {
+                Some(value) => encoder.append_pair(key, value),
+                None => encoder.append_key_only(key),
+            });
+        match (result, encoder.into_inner().error) {
+            (Err(_), Some(err)) => Err(Error::Io(err.into())),
+            (result, _) => result,
+        }
+    }
+
+    /// Handles the collisions of the pairs appended since the last check.
+    fn check(&mut self) {
+        if self.collisions == Collisions::Replace {
+            let new = self.pairs.split_off(self.checked);
+            self.pairs.retain(|(key, _)| {
+                new.iter().all(|(new_key, _)| new_key != key)
+            });
+            self.pairs.extend(new);
+        }
+        self.checked = self.pairs.len();
+    }
+
+    fn push(&mut self, key: &str, value: Option<&str>) -> Result<(), Error> {
+        if self.collisions == Collisions::Error
+            && self.pairs[..self.checked].iter().any(|(k, _)| k == key)
+        {
+            return Err(Error::KeyCollision { key: key.into() });
+        }
+        self.pairs.push((key.into(), value.map(String::from)));
+        Ok(())
+    }
+}
+
+impl Output for QueryBuilder {
+    fn append_pair(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        self.push(key, Some(value))
+    }
+
+    fn append_key_only(&mut self, key: &str) -> Result<(), Error> {
+        self.push(key, None)
+    }
+
+    fn reserve(&mut self, pairs: usize) {
+        self.pairs.reserve(pairs);
+    }
+}
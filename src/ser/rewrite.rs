@@ -0,0 +1,216 @@
+use crate::ser::part::{PartSerializer, Sink};
+use crate::ser::{Error, Kind, Location, Options};
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use form_urlencoded::{byte_serialize, parse};
+use serde::ser::Serialize;
+
+/// Rewrites the pairs of an encoded query string in place.
+///
+/// The input is only split into pairs, and the pairs left untouched are
+/// kept byte for byte, whatever their encoding. Keys are matched once
+/// decoded, and new keys and values are form-encoded.
+///
+/// ```
+/// use serde_urlencoded::ser::Rewriter;
+///
+/// let query = "utm_source=news&q=caf%e9+au+lait&fbclid=x1&page=2";
+/// let query = Rewriter::new(query)
+///     .filter(|key, _| !key.starts_with("utm_") && key != "fbclid")
+///     .rename("q", "query")
+///     .set("page", 3)?
+///     .finish();
+///
+/// assert_eq!(query, "query=caf%e9+au+lait&page=3");
+/// # Ok::<(), serde_urlencoded::ser::Error>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Rewriter<'input> {
+    segments: Vec<Segment<'input>>,
+}
+
+#[derive(Clone, Debug)]
+struct Segment<'input> {
+    /// The encoded pair, without separators.
+    raw: Cow<'input, str>,
+    /// The decoded key, `None` for an empty segment.
+    key: Option<Cow<'input, str>>,
+}
+
+impl<'input> Segment<'input> {
+    fn new(raw: Cow<'input, str>) -> Self {
+        let key = match raw {
+            Cow::Borrowed(raw) => parse(raw.as_bytes()).next().map(|(k, _)| k),
+            Cow::Owned(ref raw) => parse(raw.as_bytes())
+                .next()
+                .map(|(k, _)| Cow::Owned(k.into_owned())),
+        };
+        Segment { raw, key }
+    }
+
+    fn has_key(&self, key: &str) -> bool {
+        self.key.as_deref() == Some(key)
+    }
+}
+
+impl<'input> Rewriter<'input> {
+    /// Returns a new `Rewriter` for a query string, without its leading
+    /// `?`.
+    pub fn new(input: &'input str) -> Self {
+        let segments = if input.is_empty() {
+            Vec::new()
+        } else {
+            input
+                .split('&')
+                .map(|raw| Segment::new(Cow::Borrowed(raw)))
+                .collect()
+        };
+        Rewriter { segments }
+    }
+
+    /// Keeps only the pairs for which `f` returns `true`, given their
+    /// decoded key and value.
+    pub fn filter<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(&str, &str) -> bool,
+    {
+        self.segments.retain(|segment| {
+            match parse(segment.raw.as_bytes()).next() {
+                Some((key, value)) => f(&key, &value),
+                None => true,
+            }
+        });
+        self
+    }
+
+    /// Removes the pairs with the given key.
+    pub fn remove(mut self, key: &str) -> Self {
+        self.segments.retain(|segment| !segment.has_key(key));
+        self
+    }
+
+    /// Renames the pairs with the given key, keeping their values as they
+    /// are.
+    pub fn rename(mut self, from: &str, to: &str) -> Self {
+        for segment in &mut self.segments {
+            if segment.has_key(from) {
+                let value =
+                    segment.raw.find('=').map_or("", |i| &segment.raw[i..]);
+                let raw = format!("{}{}", encode(to), value);
+                *segment = Segment::new(Cow::Owned(raw));
+            }
+        }
+        self
+    }
+
+    /// Sets the value of a key, replacing the first pair with that key and
+    /// removing the others, or appending a pair if there is none.
+    ///
+    /// The value is serialized as the value of a struct field would be, so
+    /// `None` removes the key altogether.
+    pub fn set<T: Serialize>(self, key: &str, value: T) -> Result<Self, Error> {
+        self.update(key, value, true)
+    }
+
+    /// Replaces the value of a key like [`set`], only if the key is already
+    /// there.
+    ///
+    /// [`set`]: Rewriter::set
+    pub fn replace<T: Serialize>(
+        self,
+        key: &str,
+        value: T,
+    ) -> Result<Self, Error> {
+        self.update(key, value, false)
+    }
+
+    /// Returns the rewritten query string.
+    pub fn finish(self) -> String {
+        let mut output = String::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                output.push('&');
+            }
+            output.push_str(&segment.raw);
+        }
+        output
+    }
+
+    fn update<T: Serialize>(
+        mut self,
+        key: &str,
+        value: T,
+        append: bool,
+    ) -> Result<Self, Error> {
+        let options = Options::default();
+        let value =
+            value.serialize(PartSerializer::new(SetSink { key }, &options))?;
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(self.remove(key)),
+        };
+        let raw = format!("{}={}", encode(key), encode(&value));
+        match self
+            .segments
+            .iter()
+            .position(|segment| segment.has_key(key))
+        {
+            Some(first) => {
+                self.segments[first] = Segment::new(Cow::Owned(raw));
+                let mut i = 0;
+                self.segments.retain(|segment| {
+                    i += 1;
+                    i <= first + 1 || !segment.has_key(key)
+                });
+            }
+            None if append => self.segments.push(Segment::new(Cow::Owned(raw))),
+            None => {}
+        }
+        Ok(self)
+    }
+}
+
+fn encode(input: &str) -> String {
+    byte_serialize(input.as_bytes()).collect()
+}
+
+/// A sink returning a new value, or `None` for the key to be removed.
+struct SetSink<'key> {
+    key: &'key str,
+}
+
+impl<'key> Sink for SetSink<'key> {
+    type Ok = Option<String>;
+
+    fn serialize_static_str(
+        self,
+        value: &'static str,
+    ) -> Result<Option<String>, Error> {
+        self.serialize_str(value)
+    }
+
+    fn serialize_str(self, value: &str) -> Result<Option<String>, Error> {
+        Ok(Some(value.into()))
+    }
+
+    fn serialize_none(self) -> Result<Option<String>, Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(
+        self,
+        value: &T,
+    ) -> Result<Option<String>, Error> {
+        value.serialize(PartSerializer::new(self, &Options::default()))
+    }
+
+    fn unsupported(self, kind: Kind) -> Error {
+        Error::unsupported(kind, Location::Value, Some(self.key))
+    }
+
+    fn non_finite(self) -> Error {
+        Error::non_finite(Location::Value, Some(self.key))
+    }
+}
@@ -1,62 +1,109 @@
 use crate::ser::part::{PartSerializer, Sink};
-use crate::ser::Error;
-use form_urlencoded::Serializer as UrlEncodedSerializer;
-use form_urlencoded::Target as UrlEncodedTarget;
+use crate::ser::{Error, Kind, Location, NoneValue, Options, Output};
+use alloc::string::String;
 use serde::ser::Serialize;
-use std::str;
 
-pub struct ValueSink<'input, 'key, 'target, Target>
+pub struct ValueSink<'key, 'target, O>
 where
-    Target: UrlEncodedTarget,
+    O: Output,
 {
-    urlencoder: &'target mut UrlEncodedSerializer<'input, Target>,
+    output: &'target mut O,
     key: &'key str,
+    options: &'target Options,
 }
 
-impl<'input, 'key, 'target, Target> ValueSink<'input, 'key, 'target, Target>
+impl<'key, 'target, O> ValueSink<'key, 'target, O>
 where
-    Target: 'target + UrlEncodedTarget,
+    O: 'target + Output,
 {
     pub fn new(
-        urlencoder: &'target mut UrlEncodedSerializer<'input, Target>,
+        output: &'target mut O,
         key: &'key str,
+        options: &'target Options,
     ) -> Self {
-        ValueSink { urlencoder, key }
+        ValueSink {
+            output,
+            key,
+            options,
+        }
     }
 }
 
-impl<'input, 'key, 'target, Target> Sink
-    for ValueSink<'input, 'key, 'target, Target>
+impl<'key, 'target, O> Sink for ValueSink<'key, 'target, O>
 where
-    Target: 'target + UrlEncodedTarget,
+    O: 'target + Output,
 {
     type Ok = ();
 
     fn serialize_str(self, value: &str) -> Result<(), Error> {
-        self.urlencoder.append_pair(self.key, value);
-        Ok(())
+        if value.is_empty() && self.options.skip_empty_strings {
+            return Ok(());
+        }
+        self.output.append_pair(self.key, value)
     }
 
     fn serialize_static_str(self, value: &'static str) -> Result<(), Error> {
         self.serialize_str(value)
     }
 
-    fn serialize_string(self, value: String) -> Result<(), Error> {
-        self.serialize_str(&value)
-    }
-
     fn serialize_none(self) -> Result<Self::Ok, Error> {
-        Ok(())
+        match self.options.none_value {
+            NoneValue::Skip => Ok(()),
+            NoneValue::Empty => self.output.append_pair(self.key, ""),
+            NoneValue::KeyOnly => self.output.append_key_only(self.key),
+        }
     }
 
     fn serialize_some<T: ?Sized + Serialize>(
         self,
         value: &T,
     ) -> Result<Self::Ok, Error> {
-        value.serialize(PartSerializer::new(self))
+        let options = self.options;
+        value.serialize(PartSerializer::new(self, options))
+    }
+
+    fn unsupported(self, kind: Kind) -> Error {
+        Error::unsupported(kind, Location::Value, Some(self.key))
+    }
+
+    fn non_finite(self) -> Error {
+        Error::non_finite(Location::Value, Some(self.key))
+    }
+}
+
+/// A sink writing a single value into a string.
+pub struct StringSink;
+
+impl Sink for StringSink {
+    type Ok = String;
+
+    fn serialize_str(self, value: &str) -> Result<String, Error> {
+        Ok(value.into())
+    }
+
+    fn serialize_static_str(
+        self,
+        value: &'static str,
+    ) -> Result<String, Error> {
+        Ok(value.into())
+    }
+
+    fn serialize_none(self) -> Result<String, Error> {
+        Ok(String::new())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(
+        self,
+        value: &T,
+    ) -> Result<String, Error> {
+        value.serialize(PartSerializer::new(self, &Options::default()))
+    }
+
+    fn unsupported(self, kind: Kind) -> Error {
+        Error::unsupported(kind, Location::Value, None)
     }
 
-    fn unsupported(self) -> Error {
-        Error::Custom("unsupported value".into())
+    fn non_finite(self) -> Error {
+        Error::non_finite(Location::Value, None)
     }
 }
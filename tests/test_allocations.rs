@@ -0,0 +1,160 @@
+use serde_derive::{Deserialize, Serialize};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+#[derive(Serialize)]
+struct Search<'a> {
+    q: &'a str,
+    page: u32,
+    ratio: f64,
+    sort: char,
+    lang: Option<&'a str>,
+}
+
+#[test]
+fn struct_allocates_output_only() {
+    let search = Search {
+        q: "café au lait",
+        page: 3,
+        ratio: 0.5,
+        sort: 'é',
+        lang: Some("fr"),
+    };
+    let (output, allocations) =
+        count_allocations(|| serde_urlencoded::to_string(&search));
+
+    assert_eq!(
+        output,
+        Ok("q=caf%C3%A9+au+lait&page=3&ratio=0.5&sort=%C3%A9&lang=fr"
+            .to_owned())
+    );
+    assert_eq!(allocations, 1);
+}
+
+#[test]
+fn pairs_allocate_output_only() {
+    let keys = ["a".to_owned(), "b".to_owned(), "c".to_owned()];
+    let pairs = keys.iter().map(|key| (key.as_str(), 1)).collect::<Vec<_>>();
+    let (output, allocations) =
+        count_allocations(|| serde_urlencoded::to_string(&pairs));
+
+    assert_eq!(output, Ok("a=1&b=1&c=1".to_owned()));
+    assert_eq!(allocations, 1);
+}
+
+#[test]
+fn collect_str_does_not_allocate() {
+    struct Id(u32);
+
+    impl serde::Serialize for Id {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(&format_args!("id-{}", self.0))
+        }
+    }
+
+    let (output, allocations) =
+        count_allocations(|| serde_urlencoded::to_string([("id", Id(7))]));
+
+    assert_eq!(output, Ok("id=id-7".to_owned()));
+    assert_eq!(allocations, 1);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn bytes_mut_allocates_output_only() {
+    let search = Search {
+        q: "rust",
+        page: 1,
+        ratio: 1.5,
+        sort: 'a',
+        lang: None,
+    };
+    let (output, allocations) =
+        count_allocations(|| serde_urlencoded::to_bytes_mut(&search));
+
+    assert_eq!(
+        output.as_deref(),
+        Ok(&b"q=rust&page=1&ratio=1.5&sort=a"[..])
+    );
+    assert_eq!(allocations, 1);
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Query<'a> {
+    id: u32,
+    lang: &'a str,
+    q: String,
+}
+
+#[test]
+fn plain_keys_are_not_decoded() {
+    let input = "utm_source=a%20b&id=7&lang=fr&q=rust&_ga=%%";
+    let (output, allocations) =
+        count_allocations(|| serde_urlencoded::from_str::<Query>(input));
+
+    assert_eq!(
+        output,
+        Ok(Query {
+            id: 7,
+            lang: "fr",
+            q: "rust".to_owned(),
+        })
+    );
+    assert_eq!(allocations, 1);
+}
+
+#[test]
+fn spaces_and_escapes_are_decoded_at_once() {
+    let input = "id=7&lang=fr&q=caf%C3%A9+au+lait";
+    let (output, allocations) =
+        count_allocations(|| serde_urlencoded::from_str::<Query>(input));
+
+    assert_eq!(
+        output,
+        Ok(Query {
+            id: 7,
+            lang: "fr",
+            q: "café au lait".to_owned(),
+        })
+    );
+    assert_eq!(allocations, 1);
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "bytes")]
+
+use bytes::Bytes;
+use serde_derive::{Deserialize, Serialize};
+use serde_urlencoded::de::ByteStr;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Message {
+    from: ByteStr,
+    body: ByteStr,
+    count: u32,
+}
+
+fn contains(buf: &Bytes, part: &ByteStr) -> bool {
+    let start = buf.as_ptr() as usize;
+    let part_start = part.as_ptr() as usize;
+    part_start >= start && part_start + part.len() <= start + buf.len()
+}
+
+#[test]
+fn deserialize_shared_bytes() {
+    let input = Bytes::from_static(b"from=nox&body=hello+world&count=3");
+    let message: Message =
+        serde_urlencoded::from_shared_bytes(input.clone()).unwrap();
+
+    assert_eq!(&*message.from, "nox");
+    assert_eq!(&*message.body, "hello world");
+    assert_eq!(message.count, 3);
+    assert!(contains(&input, &message.from));
+    assert!(!contains(&input, &message.body));
+}
+
+#[test]
+fn deserialize_shared_bytes_owned_types() {
+    let input = Bytes::from(b"first=23&last=42".to_vec());
+    let result = vec![("first".to_owned(), 23), ("last".to_owned(), 42)];
+
+    assert_eq!(serde_urlencoded::from_shared_bytes(input), Ok(result));
+}
+
+#[test]
+fn deserialize_byte_str_without_shared_bytes() {
+    let input = Bytes::from_static(b"from=nox&body=hi&count=1");
+    let text = std::str::from_utf8(&input).unwrap();
+    let message: Message = serde_urlencoded::from_str(text).unwrap();
+
+    assert_eq!(&*message.from, "nox");
+    assert!(!contains(&input, &message.from));
+}
+
+#[test]
+fn serialize_bytes_mut() {
+    let message = Message {
+        from: "nox".into(),
+        body: "hello world".into(),
+        count: 3,
+    };
+    let buf = serde_urlencoded::to_bytes_mut(&message).unwrap();
+
+    assert_eq!(&buf[..], b"from=nox&body=hello+world&count=3");
+}
@@ -1,4 +1,10 @@
-use serde_derive::Deserialize;
+use serde::de::{Deserialize, DeserializeSeed};
+use serde_derive::{Deserialize, Serialize};
+use serde_urlencoded::de::{
+    from_value_str, Error, KeyMatching, Rest, ValueDeserializer,
+};
+use serde_urlencoded::Deserializer;
+use std::collections::BTreeMap;
 
 #[derive(Deserialize, Debug, PartialEq)]
 struct NewType<T>(T);
@@ -34,6 +40,7 @@ fn deserialize_borrowed_str() {
     assert_eq!(serde_urlencoded::from_str("first=23&last=42"), Ok(result));
 }
 
+#[cfg(feature = "std")]
 #[test]
 fn deserialize_reader() {
     let result = vec![("first".to_owned(), 23), ("last".to_owned(), 42)];
@@ -86,3 +93,584 @@ fn deserialize_unit_enum() {
 fn deserialize_unit_type() {
     assert_eq!(serde_urlencoded::from_str(""), Ok(()));
 }
+
+#[test]
+fn deserialize_url_query_str() {
+    let result = vec![("first".to_owned(), 23), ("last".to_owned(), 42)];
+
+    assert_eq!(
+        serde_urlencoded::from_url_query_str("?first=23&last=42#fragment"),
+        Ok(result.clone())
+    );
+    assert_eq!(
+        serde_urlencoded::from_url_query_str("first=23&last=42"),
+        Ok(result)
+    );
+    assert_eq!(serde_urlencoded::from_url_query_str("?#first=23"), Ok(()));
+}
+
+struct Prefixed<'a>(&'a str);
+
+impl<'de, 'a> DeserializeSeed<'de> for Prefixed<'a> {
+    type Value = Vec<(String, String)>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let pairs = Vec::<(String, String)>::deserialize(deserializer)?;
+        Ok(pairs
+            .into_iter()
+            .map(|(k, v)| (format!("{}{}", self.0, k), v))
+            .collect())
+    }
+}
+
+#[test]
+fn deserialize_seed() {
+    let result = vec![
+        ("tenant.first".to_owned(), "23".to_owned()),
+        ("tenant.last".to_owned(), "42".to_owned()),
+    ];
+
+    assert_eq!(
+        serde_urlencoded::de::from_str_seed(
+            "first=23&last=42",
+            Prefixed("tenant.")
+        ),
+        Ok(result.clone())
+    );
+    assert_eq!(
+        serde_urlencoded::de::from_bytes_seed(
+            b"first=23&last=42",
+            Prefixed("tenant.")
+        ),
+        Ok(result.clone())
+    );
+    #[cfg(feature = "std")]
+    assert_eq!(
+        serde_urlencoded::de::from_reader_seed(
+            b"first=23&last=42" as &[_],
+            Prefixed("tenant.")
+        ),
+        Ok(result)
+    );
+}
+
+#[test]
+fn deserializer_end() {
+    let mut deserializer = Deserializer::from_str("first=23&last=42");
+    let pairs = Vec::<(String, u32)>::deserialize(&mut deserializer);
+    assert_eq!(pairs.map(|pairs| pairs.len()), Ok(2));
+    assert_eq!(deserializer.end(), Ok(()));
+
+    let mut deserializer = Deserializer::from_bytes(b"first=23&last=42");
+    let pair = <[(String, u32); 1]>::deserialize(&mut deserializer);
+    assert_eq!(pair, Ok([("first".to_owned(), 23)]));
+    assert!(deserializer.end().is_err());
+}
+
+#[test]
+fn deserialize_pair_length() {
+    assert!(serde_urlencoded::from_str::<Vec<(String,)>>("first=23").is_err());
+    assert!(serde_urlencoded::from_str::<Vec<(String, String, String)>>(
+        "first=23"
+    )
+    .is_err());
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Route {
+    id: u32,
+    slug: String,
+    draft: Option<bool>,
+}
+
+#[test]
+fn deserialize_from_pairs() {
+    let captures = vec![("id", "42"), ("slug", "hello world")];
+    let route = Route::deserialize(Deserializer::from_pairs(captures));
+
+    assert_eq!(
+        route,
+        Ok(Route {
+            id: 42,
+            slug: "hello world".to_owned(),
+            draft: None,
+        })
+    );
+
+    let owned = vec![("id".to_owned(), "7".to_owned())];
+    let pairs =
+        Vec::<(String, u32)>::deserialize(Deserializer::from_pairs(owned));
+    assert_eq!(pairs, Ok(vec![("id".to_owned(), 7)]));
+}
+
+#[test]
+fn deserialize_from_pairs_lazily() {
+    let pairs = std::iter::repeat(("id", "1"));
+    let pair = <[(&str, u32); 2]>::deserialize(Deserializer::from_pairs(pairs));
+
+    assert_eq!(pair, Ok([("id", 1), ("id", 1)]));
+}
+
+#[test]
+fn deserialize_from_pairs_errors() {
+    let pairs = vec![("id", "x"), ("slug", "a")];
+
+    assert!(Route::deserialize(Deserializer::from_pairs(pairs)).is_err());
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Tracked {
+    id: u32,
+    ratio: f64,
+    q: String,
+}
+
+#[test]
+fn deserialize_escaped_numbers() {
+    assert_eq!(
+        serde_urlencoded::from_str("id=%34%32&ratio=%2B1.5&q=a+b%2Bc"),
+        Ok(Tracked {
+            id: 42,
+            ratio: 1.5,
+            q: "a b+c".to_owned(),
+        })
+    );
+    assert!(serde_urlencoded::from_str::<Tracked>("id=4+2&ratio=1&q=").is_err());
+}
+
+#[test]
+fn deserialize_escaped_keys() {
+    assert_eq!(
+        serde_urlencoded::from_str("%69d=1&ra%74io=0.5&q=%FF+a%2B"),
+        Ok(Tracked {
+            id: 1,
+            ratio: 0.5,
+            q: "\u{FFFD} a+".to_owned(),
+        })
+    );
+}
+
+#[test]
+fn deserialize_skips_unknown_values() {
+    let input = b"utm_source=%FF%FE&id=1&ratio=0.5&_ga=%%%&q=%C3%A9";
+
+    assert_eq!(
+        serde_urlencoded::from_bytes(input),
+        Ok(Tracked {
+            id: 1,
+            ratio: 0.5,
+            q: "é".to_owned(),
+        })
+    );
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Profile {
+    name: String,
+    age: u32,
+    nickname: Option<String>,
+}
+
+#[test]
+fn deserialize_into_keeps_absent_fields() {
+    let mut profile = Profile {
+        name: "Anthony".to_owned(),
+        age: 30,
+        nickname: Some("nox".to_owned()),
+    };
+    serde_urlencoded::from_str_into("age=31", &mut profile).unwrap();
+
+    assert_eq!(
+        profile,
+        Profile {
+            name: "Anthony".to_owned(),
+            age: 31,
+            nickname: Some("nox".to_owned()),
+        }
+    );
+}
+
+#[test]
+fn deserialize_into_sets_options() {
+    let mut profile = Profile {
+        name: "Anthony".to_owned(),
+        age: 30,
+        nickname: None,
+    };
+    serde_urlencoded::from_bytes_into(b"nickname=anne&name=Ann", &mut profile)
+        .unwrap();
+
+    assert_eq!(
+        profile,
+        Profile {
+            name: "Ann".to_owned(),
+            age: 30,
+            nickname: Some("anne".to_owned()),
+        }
+    );
+}
+
+#[test]
+fn deserialize_into_flattened_fields() {
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+        language: String,
+    }
+
+    #[derive(Deserialize, Serialize, Debug, PartialEq)]
+    struct Account {
+        name: String,
+        #[serde(flatten)]
+        settings: Settings,
+    }
+
+    let mut account = Account {
+        name: "Anthony".to_owned(),
+        settings: Settings {
+            theme: "dark".to_owned(),
+            language: "en".to_owned(),
+        },
+    };
+    serde_urlencoded::from_str_into("language=fr", &mut account).unwrap();
+
+    assert_eq!(
+        account,
+        Account {
+            name: "Anthony".to_owned(),
+            settings: Settings {
+                theme: "dark".to_owned(),
+                language: "fr".to_owned(),
+            },
+        }
+    );
+}
+
+#[test]
+fn deserialize_into_error_keeps_value() {
+    let mut profile = Profile {
+        name: "Anthony".to_owned(),
+        age: 30,
+        nickname: Some("nox".to_owned()),
+    };
+    let result =
+        serde_urlencoded::from_str_into("name=Ann&age=old", &mut profile);
+
+    assert!(result.is_err());
+    assert_eq!(
+        profile,
+        Profile {
+            name: "Anthony".to_owned(),
+            age: 30,
+            nickname: Some("nox".to_owned()),
+        }
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Filter {
+    name: String,
+    tag: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Page {
+    size: u32,
+    number: u32,
+}
+
+#[test]
+fn deserialize_key_prefix() {
+    let input = "filter_name=a+b&page.size=20&utm_source=x&filter_tag=rust\
+                 &page.number=3";
+
+    let mut deserializer = Deserializer::from_str(input).key_prefix("filter_");
+    assert_eq!(
+        Filter::deserialize(&mut deserializer),
+        Ok(Filter {
+            name: "a b".to_owned(),
+            tag: Some("rust".to_owned()),
+        })
+    );
+    assert_eq!(deserializer.end(), Ok(()));
+
+    let deserializer = Deserializer::from_str(input).key_prefix("page.");
+    assert_eq!(
+        Page::deserialize(deserializer),
+        Ok(Page {
+            size: 20,
+            number: 3,
+        })
+    );
+}
+
+#[test]
+fn deserialize_escaped_key_prefix() {
+    let input = "page%2Esize=20&page.number=3&a+b.x=1&a%20b.y=2&a%2Bb.z=3";
+
+    let deserializer = Deserializer::from_str(input).key_prefix("page.");
+    assert_eq!(
+        Page::deserialize(deserializer),
+        Ok(Page {
+            size: 20,
+            number: 3,
+        })
+    );
+
+    let deserializer = Deserializer::from_str(input).key_prefix("a b.");
+    assert_eq!(
+        Vec::<(String, u32)>::deserialize(deserializer),
+        Ok(vec![("x".to_owned(), 1), ("y".to_owned(), 2)])
+    );
+
+    let deserializer = Deserializer::from_str(input).key_prefix("a+b.");
+    assert_eq!(
+        Vec::<(String, u32)>::deserialize(deserializer),
+        Ok(vec![("z".to_owned(), 3)])
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Search {
+    query: String,
+    page: Option<u32>,
+}
+
+#[test]
+fn record_unknown_keys() {
+    let input = "query=rust&pgae=2&utm_source=news%20letter&page=3";
+    let mut deserializer = Deserializer::from_str(input).record_unknown_keys();
+    let search = Search::deserialize(&mut deserializer).unwrap();
+
+    assert_eq!(
+        search,
+        Search {
+            query: "rust".to_owned(),
+            page: Some(3),
+        }
+    );
+    let unknown = deserializer
+        .unknown_keys()
+        .iter()
+        .map(|key| (key.key(), key.offset()))
+        .collect::<Vec<_>>();
+    assert_eq!(unknown, [("pgae", Some(11)), ("utm_source", Some(18))]);
+}
+
+#[test]
+fn unknown_keys_not_recorded_by_default() {
+    let mut deserializer = Deserializer::from_str("query=rust&pgae=2");
+    Search::deserialize(&mut deserializer).unwrap();
+
+    assert!(deserializer.unknown_keys().is_empty());
+}
+
+#[test]
+fn unknown_keys_from_parse() {
+    let parse = form_urlencoded::parse(b"query=rust&pgae=2");
+    let mut deserializer = Deserializer::new(parse).record_unknown_keys();
+    Search::deserialize(&mut deserializer).unwrap();
+
+    let unknown = deserializer.unknown_keys();
+    assert_eq!(unknown.len(), 1);
+    assert_eq!(unknown[0].key(), "pgae");
+    assert_eq!(unknown[0].offset(), None);
+}
+
+#[test]
+fn unknown_keys_keep_prefix() {
+    let input = "f.query=rust&f.qery=x&f.page=3&other=1";
+    let mut deserializer = Deserializer::from_str(input)
+        .key_prefix("f.")
+        .record_unknown_keys();
+
+    assert_eq!(
+        Search::deserialize(&mut deserializer),
+        Ok(Search {
+            query: "rust".to_owned(),
+            page: Some(3),
+        })
+    );
+    let unknown = deserializer.unknown_keys();
+    assert_eq!(unknown.len(), 1);
+    assert_eq!(unknown[0].key(), "f.qery");
+    assert_eq!(unknown[0].offset(), Some(13));
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Filters {
+    query: String,
+    #[serde(flatten)]
+    rest: Rest,
+}
+
+#[test]
+fn deserialize_rest() {
+    let filters: Filters =
+        serde_urlencoded::from_str("tag=a&query=rust&lang=fr&tag=b").unwrap();
+
+    assert_eq!(filters.query, "rust");
+    assert_eq!(
+        filters.rest,
+        Rest(vec![
+            ("tag".to_owned(), "a".to_owned()),
+            ("lang".to_owned(), "fr".to_owned()),
+            ("tag".to_owned(), "b".to_owned()),
+        ])
+    );
+    assert_eq!(
+        serde_urlencoded::to_string(&filters),
+        Ok("query=rust&tag=a&lang=fr&tag=b".to_owned())
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Sort {
+    Asc,
+    Desc,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct SortedPage {
+    page_size: u32,
+    sort: Option<Sort>,
+}
+
+fn sorted_page(
+    input: &str,
+    matching: KeyMatching,
+) -> Result<SortedPage, Error> {
+    SortedPage::deserialize(
+        Deserializer::from_str(input).key_matching(matching),
+    )
+}
+
+#[test]
+fn key_matching_exact_by_default() {
+    let result = SortedPage::deserialize(Deserializer::from_str("pageSize=10"));
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn key_matching_ascii_case_insensitive() {
+    let result =
+        sorted_page("PAGE_SIZE=10&Sort=asc", KeyMatching::AsciiCaseInsensitive);
+
+    assert_eq!(
+        result,
+        Ok(SortedPage {
+            page_size: 10,
+            sort: Some(Sort::Asc),
+        })
+    );
+    assert!(
+        sorted_page("pageSize=10", KeyMatching::AsciiCaseInsensitive).is_err()
+    );
+}
+
+#[test]
+fn key_matching_convention_insensitive() {
+    for input in &["pageSize=10", "PageSize=10", "page-size=10", "page_size=10"]
+    {
+        let result = sorted_page(input, KeyMatching::ConventionInsensitive);
+
+        assert_eq!(
+            result,
+            Ok(SortedPage {
+                page_size: 10,
+                sort: None,
+            }),
+            "{}",
+            input
+        );
+    }
+}
+
+#[test]
+fn key_matching_variants_in_pair_sequences() {
+    let deserializer = Deserializer::from_str("a=DESC&b=asc")
+        .key_matching(KeyMatching::AsciiCaseInsensitive);
+    let pairs = Vec::<(String, Sort)>::deserialize(deserializer).unwrap();
+
+    assert_eq!(
+        pairs,
+        [("a".to_owned(), Sort::Desc), ("b".to_owned(), Sort::Asc)]
+    );
+}
+
+#[test]
+fn key_matching_variants_as_map_keys() {
+    let deserializer = Deserializer::from_str("asc=1&DESC=2")
+        .key_matching(KeyMatching::AsciiCaseInsensitive);
+    let map = BTreeMap::<Sort, u32>::deserialize(deserializer).unwrap();
+
+    assert_eq!(map.get(&Sort::Asc), Some(&1));
+    assert_eq!(map.get(&Sort::Desc), Some(&2));
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[allow(non_snake_case)]
+struct Clashing {
+    page_size: u32,
+    pageSize: u32,
+}
+
+#[test]
+fn key_matching_exact_match_wins() {
+    let deserializer = Deserializer::from_str("page_size=1&pageSize=2")
+        .key_matching(KeyMatching::ConventionInsensitive);
+    let result = Clashing::deserialize(deserializer);
+
+    assert_eq!(
+        result,
+        Ok(Clashing {
+            page_size: 1,
+            pageSize: 2,
+        })
+    );
+}
+
+#[test]
+fn key_matching_ambiguous_key() {
+    let deserializer = Deserializer::from_str("PageSize=1")
+        .key_matching(KeyMatching::ConventionInsensitive);
+    let error = Clashing::deserialize(deserializer).unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "`PageSize` is ambiguous between `page_size` and `pageSize`"
+    );
+}
+
+#[test]
+fn deserialize_value() {
+    assert_eq!(from_value_str("B"), Ok(X::B));
+    assert_eq!(from_value_str("7"), Ok(NewType(7)));
+    assert_eq!(from_value_str("-1.5"), Ok(-1.5));
+    assert_eq!(from_value_str("é"), Ok('é'));
+    assert_eq!(from_value_str("3"), Ok(Some(3)));
+}
+
+#[test]
+fn deserialize_value_is_not_decoded() {
+    assert_eq!(from_value_str("a+b%26c"), Ok("a+b%26c"));
+}
+
+#[test]
+fn deserialize_value_errors() {
+    assert!(from_value_str::<Option<u32>>("").is_err());
+    assert!(from_value_str::<bool>("yes").is_err());
+    assert!(from_value_str::<X>("D").is_err());
+}
+
+#[test]
+fn deserialize_value_owned() {
+    let value = String::from("42");
+    let deserializer = ValueDeserializer::new(value);
+
+    assert_eq!(u8::deserialize(deserializer), Ok(42));
+}
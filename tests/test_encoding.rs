@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use serde_urlencoded::de::{Decoding, Deserializer};
+use serde_urlencoded::ser::{Encoder, Encoding, Serializer};
+
+fn encode<T: Serialize>(input: T, encoding: Encoding) -> String {
+    let mut encoder = Encoder::new(String::new()).encoding(encoding);
+    input.serialize(Serializer::new(&mut encoder)).unwrap();
+    encoder.into_inner()
+}
+
+fn decode(input: &str, decoding: Decoding) -> Vec<(String, String)> {
+    let deserializer = Deserializer::from_str(input).decoding(decoding);
+    Vec::deserialize(deserializer).unwrap()
+}
+
+#[test]
+fn form_encoding_matches_to_string() {
+    let pairs = [("a b", "c+d"), ("*-._~", "é&=")];
+
+    assert_eq!(
+        encode(pairs, Encoding::form()),
+        serde_urlencoded::to_string(pairs).unwrap()
+    );
+}
+
+#[test]
+fn query_encoding() {
+    let pairs = [("a b", "c+d"), ("*-._~", "é&=")];
+
+    assert_eq!(
+        encode(pairs, Encoding::query()),
+        "a%20b=c%2Bd&%2A-._~=%C3%A9%26%3D"
+    );
+}
+
+#[test]
+fn query_decoding() {
+    assert_eq!(
+        decode("a+b=c%2Bd+e&f=%20", Decoding::Query),
+        [
+            ("a+b".to_owned(), "c+d+e".to_owned()),
+            ("f".to_owned(), " ".to_owned()),
+        ]
+    );
+    assert_eq!(
+        decode("a+b=c%2Bd+e", Decoding::Form),
+        [("a b".to_owned(), "c+d e".to_owned())]
+    );
+}
+
+#[test]
+fn query_decoding_edge_cases() {
+    assert_eq!(
+        decode("&&k&=v&x==y&", Decoding::Query),
+        [
+            ("k".to_owned(), "".to_owned()),
+            ("".to_owned(), "v".to_owned()),
+            ("x".to_owned(), "=y".to_owned()),
+        ]
+    );
+    assert_eq!(
+        decode("bad=%ff%2", Decoding::Query),
+        [("bad".to_owned(), "\u{fffd}%2".to_owned())]
+    );
+}
+
+#[test]
+fn query_round_trip() {
+    let pairs = vec![
+        ("plus".to_owned(), "1+1 = 2".to_owned()),
+        ("path".to_owned(), "/a/b?c#d".to_owned()),
+        ("unicode".to_owned(), "ça ✓".to_owned()),
+    ];
+    let encoded = encode(&pairs, Encoding::query());
+
+    assert!(!encoded.contains('+'));
+    assert_eq!(decode(&encoded, Decoding::Query), pairs);
+}
+
+fn tricky_pairs() -> Vec<(String, String)> {
+    vec![
+        ("path".to_owned(), "/a/b:c@d,e;f?g".to_owned()),
+        ("reserved".to_owned(), "&=%#+ ".to_owned()),
+        ("unicode".to_owned(), "çà ✓ 𝄞".to_owned()),
+        ("marks".to_owned(), "!$'()*~".to_owned()),
+    ]
+}
+
+#[test]
+fn custom_encoding() {
+    let pairs = [("path", "/a,b:c@d é")];
+
+    assert_eq!(
+        encode(pairs, Encoding::query().keep(",:/@")),
+        "path=/a,b:c@d%20%C3%A9"
+    );
+    assert_eq!(
+        encode(pairs, Encoding::form().keep("/").lowercase_hex(true)),
+        "path=/a%2cb%3ac%40d+%c3%a9"
+    );
+    assert_eq!(
+        encode(pairs, Encoding::query().iri(true)),
+        "path=%2Fa%2Cb%3Ac%40d%20é"
+    );
+}
+
+#[test]
+fn custom_encoding_round_trip() {
+    let pairs = tricky_pairs();
+    let form_encodings = [
+        Encoding::form().keep(",:/@;?!$'()~"),
+        Encoding::form().iri(true).lowercase_hex(true),
+    ];
+    let query_encodings = [
+        Encoding::query().keep(",:/@;?!$'()*").iri(true),
+        Encoding::query().lowercase_hex(true),
+    ];
+
+    for &encoding in form_encodings.iter().chain(&query_encodings) {
+        let encoded = encode(&pairs, encoding);
+
+        assert_eq!(decode(&encoded, Decoding::Form), pairs, "{}", encoded);
+        assert_eq!(
+            serde_urlencoded::from_str::<Vec<(String, String)>>(&encoded),
+            Ok(pairs.clone())
+        );
+    }
+    for &encoding in &query_encodings {
+        let encoded = encode(&pairs, encoding);
+
+        assert_eq!(decode(&encoded, Decoding::Query), pairs, "{}", encoded);
+    }
+}
+
+#[test]
+#[should_panic(expected = "'&' cannot be left unescaped")]
+fn keep_rejects_delimiters() {
+    let _ = Encoding::query().keep(",&");
+}
+
+#[test]
+#[should_panic(expected = "cannot be left unescaped")]
+fn keep_rejects_plus() {
+    let _ = Encoding::query().keep("+");
+}
+
+#[test]
+#[should_panic(expected = "'<' cannot be left unescaped")]
+fn keep_rejects_characters_outside_queries() {
+    let _ = Encoding::query().keep(",<\"");
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "http")]
+
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Login {
+    user: String,
+    remember: bool,
+}
+
+#[test]
+fn deserialize_uri() {
+    let uri: http::Uri = "/login?user=nox&remember=true".parse().unwrap();
+
+    assert_eq!(
+        serde_urlencoded::from_uri(&uri),
+        Ok(Login {
+            user: "nox".to_owned(),
+            remember: true,
+        })
+    );
+}
+
+#[test]
+fn deserialize_uri_without_query() {
+    let uri: http::Uri = "/login".parse().unwrap();
+
+    assert_eq!(serde_urlencoded::from_uri(&uri), Ok(()));
+}
+
+#[test]
+fn serialize_request_round_trip() {
+    let login = Login {
+        user: "nox".to_owned(),
+        remember: true,
+    };
+    let builder = http::Request::post("/login");
+    let request = serde_urlencoded::to_request(builder, &login).unwrap();
+
+    assert_eq!(
+        request.headers()[http::header::CONTENT_TYPE],
+        "application/x-www-form-urlencoded"
+    );
+    assert_eq!(request.body(), "user=nox&remember=true");
+    assert_eq!(serde_urlencoded::from_request(&request), Ok(login));
+}
+
+#[test]
+fn deserialize_request_content_type() {
+    let request = |content_type: &str| {
+        http::Request::post("/login")
+            .header(http::header::CONTENT_TYPE, content_type)
+            .body(b"user=nox&remember=true".to_vec())
+            .unwrap()
+    };
+
+    for accepted in &[
+        "application/x-www-form-urlencoded",
+        "Application/X-WWW-Form-URLEncoded",
+        "application/x-www-form-urlencoded; charset=utf-8",
+        "application/x-www-form-urlencoded;charset=\"UTF-8\"",
+    ] {
+        assert_eq!(
+            serde_urlencoded::from_request(&request(accepted)),
+            Ok(Login {
+                user: "nox".to_owned(),
+                remember: true,
+            }),
+            "{}",
+            accepted
+        );
+    }
+    for rejected in &[
+        "application/json",
+        "multipart/form-data; boundary=x",
+        "application/x-www-form-urlencoded; charset=iso-8859-1",
+    ] {
+        assert!(
+            serde_urlencoded::from_request::<Login, _>(&request(rejected))
+                .is_err(),
+            "{}",
+            rejected
+        );
+    }
+
+    let missing = http::Request::post("/login").body("user=nox").unwrap();
+    assert!(serde_urlencoded::from_request::<Login, _>(&missing).is_err());
+}
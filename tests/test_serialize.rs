@@ -1,4 +1,10 @@
+use serde::ser::Serialize;
 use serde_derive::Serialize;
+use serde_urlencoded::ser::{
+    to_value_string, Collisions, Encoding, Error, FloatFormat, Kind, Location,
+    NoneValue, PairOrder, QueryBuilder, Rewriter, Serializer,
+};
+use std::collections::BTreeMap;
 
 #[derive(Serialize)]
 struct NewType<T>(T);
@@ -102,3 +108,717 @@ fn serialize_unit_struct() {
 fn serialize_unit_type() {
     assert_eq!(serde_urlencoded::to_string(()), Ok("".to_owned()));
 }
+
+type UrlEncoder<'a> = form_urlencoded::Serializer<'a, String>;
+
+fn to_string_with<T, F>(input: T, configure: F) -> Result<String, Error>
+where
+    T: Serialize,
+    F: for<'a, 'b> FnOnce(
+        Serializer<'a, UrlEncoder<'b>>,
+    ) -> Serializer<'a, UrlEncoder<'b>>,
+{
+    let mut urlencoder = form_urlencoded::Serializer::new(String::new());
+    input.serialize(configure(Serializer::new(&mut urlencoder)))?;
+    Ok(urlencoder.finish())
+}
+
+#[test]
+fn serialize_float_shortest() {
+    let params = &[("one", 1.0), ("big", 1e20), ("small", 1.5e-7)];
+    assert_eq!(
+        serde_urlencoded::to_string(params),
+        Ok("one=1.0&big=1e20&small=1.5e-7".to_owned())
+    );
+}
+
+#[test]
+fn serialize_float_plain() {
+    let params = &[("one", 1.0), ("big", 1e20), ("small", 1.5e-7)];
+    assert_eq!(
+        to_string_with(params, |s| s.float_format(FloatFormat::Plain)),
+        Ok("one=1.0&big=100000000000000000000.0&small=0.00000015".to_owned())
+    );
+}
+
+#[test]
+fn serialize_float_minimal() {
+    let params = &[("one", 1.0), ("big", 1e20), ("half", 0.5)];
+    assert_eq!(
+        to_string_with(params, |s| s.float_format(FloatFormat::Minimal)),
+        Ok("one=1&big=100000000000000000000&half=0.5".to_owned())
+    );
+}
+
+#[test]
+fn serialize_float_fixed() {
+    let params = &[("one", 1.0), ("third", 1.0 / 3.0), ("big", 1e20)];
+    assert_eq!(
+        to_string_with(params, |s| s.float_format(FloatFormat::Fixed(2))),
+        Ok("one=1.00&third=0.33&big=100000000000000000000.00".to_owned())
+    );
+}
+
+#[test]
+fn serialize_float_non_finite() {
+    let params = &[("nan", f64::NAN), ("inf", f64::INFINITY)];
+    assert_eq!(
+        serde_urlencoded::to_string(params),
+        Ok("nan=NaN&inf=inf".to_owned())
+    );
+
+    let params = &[("finite", 1.0), ("ratio", f64::NEG_INFINITY)];
+    let err =
+        to_string_with(params, |s| s.reject_non_finite(true)).unwrap_err();
+    assert_eq!(
+        err,
+        Error::NonFinite {
+            location: Location::Value,
+            key: Some("ratio".to_owned()),
+        }
+    );
+    assert_eq!(err.to_string(), "non-finite float as value for key `ratio`");
+}
+
+#[derive(Serialize)]
+struct Assoc {
+    a: u32,
+    b: Option<String>,
+    c: Option<String>,
+}
+
+#[test]
+fn serialize_to_pairs() {
+    let params = Assoc {
+        a: 1,
+        b: Some("x y".to_owned()),
+        c: None,
+    };
+
+    assert_eq!(
+        serde_urlencoded::to_pairs(&params),
+        Ok(vec![
+            ("a".to_owned(), "1".to_owned()),
+            ("b".to_owned(), "x y".to_owned()),
+        ])
+    );
+}
+
+#[test]
+fn serialize_map_to_pairs() {
+    let mut map = BTreeMap::new();
+    map.insert("k&y", "v=l");
+
+    assert_eq!(
+        serde_urlencoded::to_pairs(&map),
+        Ok(vec![("k&y".to_owned(), "v=l".to_owned())])
+    );
+}
+
+#[derive(Serialize)]
+struct Failing {
+    a: u32,
+    b: Vec<u32>,
+    c: u32,
+}
+
+#[test]
+fn for_each_pair_stops_at_error() {
+    let params = Failing {
+        a: 1,
+        b: vec![2],
+        c: 3,
+    };
+    let mut pairs = Vec::new();
+    let result = serde_urlencoded::for_each_pair(&params, |key, value| {
+        pairs.push(format!("{}={}", key, value))
+    });
+
+    assert!(result.is_err());
+    assert_eq!(pairs, ["a=1"]);
+}
+
+#[test]
+fn serialize_to_vec() {
+    let params = &[("cheese", "comté"), ("k&y", "v=l")];
+
+    assert_eq!(
+        serde_urlencoded::to_vec(params),
+        Ok(b"cheese=comt%C3%A9&k%26y=v%3Dl".to_vec())
+    );
+    assert_eq!(serde_urlencoded::to_vec(()), Ok(Vec::new()));
+}
+
+#[test]
+fn serialize_to_fmt() {
+    struct Query<'a>(&'a [(&'a str, u32)]);
+
+    impl std::fmt::Display for Query<'_> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            serde_urlencoded::to_fmt(f, self.0).map_err(|_| std::fmt::Error)
+        }
+    }
+
+    let query = Query(&[("a", 1), ("b", 2)]);
+    assert_eq!(format!("?{}", query), "?a=1&b=2");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn serialize_to_writer() {
+    let mut body = Vec::new();
+    serde_urlencoded::to_writer(&mut body, [("q", "a b")]).unwrap();
+
+    assert_eq!(body, b"q=a+b");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn serialize_to_writer_io_error() {
+    struct Full;
+
+    impl std::io::Write for Full {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::WriteZero.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    match serde_urlencoded::to_writer(Full, [("q", "rust")]) {
+        Err(Error::Io(err)) => {
+            assert_eq!(err.kind(), std::io::ErrorKind::WriteZero)
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn display_query() {
+    let params = Assoc {
+        a: 1,
+        b: Some("x&y".to_owned()),
+        c: None,
+    };
+    let display = serde_urlencoded::display(&params);
+
+    assert_eq!(format!("/path?{}", display), "/path?a=1&b=x%26y");
+    assert_eq!(display.to_string(), "a=1&b=x%26y");
+}
+
+#[test]
+fn display_error() {
+    use std::fmt::Write;
+
+    let params = Failing {
+        a: 1,
+        b: vec![2],
+        c: 3,
+    };
+    let mut output = String::new();
+
+    assert!(write!(output, "{}", serde_urlencoded::display(&params)).is_err());
+    assert_eq!(
+        serde_urlencoded::try_display(&params).map(|_| ()),
+        Err(Error::Unsupported {
+            kind: Kind::Seq,
+            location: Location::Value,
+            key: Some("b".to_owned()),
+        })
+    );
+}
+
+#[test]
+fn try_display_query() {
+    let display = serde_urlencoded::try_display(&[("q", "rust")]).unwrap();
+
+    assert_eq!(format!("?{}", display), "?q=rust");
+}
+
+fn to_string_ordered<T: Serialize>(input: T, order: PairOrder) -> String {
+    to_string_with(input, |serializer| serializer.pair_order(order)).unwrap()
+}
+
+#[derive(Serialize)]
+struct Unsorted {
+    zeta: u32,
+    alpha: u32,
+    mid: Option<Vec<u32>>,
+}
+
+#[test]
+fn serialize_sorted_pairs() {
+    let pairs = [("b", "2"), ("a", "3"), ("b", "1"), ("a", "1")];
+
+    assert_eq!(
+        to_string_ordered(pairs, PairOrder::Insertion),
+        "b=2&a=3&b=1&a=1"
+    );
+    assert_eq!(
+        to_string_ordered(pairs, PairOrder::ByKey),
+        "a=3&a=1&b=2&b=1"
+    );
+    assert_eq!(
+        to_string_ordered(pairs, PairOrder::ByKeyThenValue),
+        "a=1&a=3&b=1&b=2"
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn serialize_sorted_map() {
+    let map = (0..32)
+        .map(|i| (format!("k{:02}", i), i))
+        .collect::<std::collections::HashMap<_, _>>();
+    let expected = (0..32)
+        .map(|i| format!("k{:02}={}", i, i))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    assert_eq!(to_string_ordered(&map, PairOrder::ByKey), expected);
+}
+
+#[test]
+fn serialize_sorted_struct() {
+    let value = Unsorted {
+        zeta: 1,
+        alpha: 2,
+        mid: None,
+    };
+
+    assert_eq!(
+        to_string_ordered(&value, PairOrder::ByKey),
+        "alpha=2&zeta=1"
+    );
+}
+
+#[test]
+fn serialize_sorted_error() {
+    let value = Unsorted {
+        zeta: 1,
+        alpha: 2,
+        mid: Some(vec![1]),
+    };
+    let mut urlencoder = form_urlencoded::Serializer::new(String::new());
+    let serializer =
+        Serializer::new(&mut urlencoder).pair_order(PairOrder::ByKey);
+
+    assert!(value.serialize(serializer).is_err());
+    assert_eq!(urlencoder.finish(), "");
+}
+
+fn to_string_none<T: Serialize>(input: T, none_value: NoneValue) -> String {
+    to_string_with(input, |serializer| serializer.none_value(none_value))
+        .unwrap()
+}
+
+#[test]
+fn serialize_none_values() {
+    let value = Assoc {
+        a: 1,
+        b: None,
+        c: Some(String::new()),
+    };
+
+    assert_eq!(to_string_none(&value, NoneValue::Skip), "a=1&c=");
+    assert_eq!(to_string_none(&value, NoneValue::Empty), "a=1&b=&c=");
+    assert_eq!(to_string_none(&value, NoneValue::KeyOnly), "a=1&b&c=");
+}
+
+#[test]
+fn serialize_none_pair_and_map_values() {
+    let pairs = [("x", None), ("y", Some("1"))];
+    let mut map = BTreeMap::new();
+    map.insert("m", None::<u32>);
+    map.insert("n", Some(2));
+
+    assert_eq!(to_string_none(pairs, NoneValue::Skip), "y=1");
+    assert_eq!(to_string_none(pairs, NoneValue::Empty), "x=&y=1");
+    assert_eq!(to_string_none(pairs, NoneValue::KeyOnly), "x&y=1");
+    assert_eq!(to_string_none(&map, NoneValue::Empty), "m=&n=2");
+    assert_eq!(to_string_none(&map, NoneValue::KeyOnly), "m&n=2");
+}
+
+#[test]
+fn serialize_skip_empty_strings() {
+    let value = Assoc {
+        a: 1,
+        b: Some(String::new()),
+        c: None,
+    };
+    let result = to_string_with(&value, |serializer| {
+        serializer
+            .skip_empty_strings(true)
+            .none_value(NoneValue::KeyOnly)
+    });
+
+    assert_eq!(result, Ok("a=1&c".to_owned()));
+}
+
+#[test]
+fn serialize_sorted_key_only() {
+    let pairs = [("b", Some("1")), ("a", None), ("a", Some(""))];
+    let result = to_string_with(pairs, |serializer| {
+        serializer
+            .none_value(NoneValue::KeyOnly)
+            .pair_order(PairOrder::ByKeyThenValue)
+    });
+
+    assert_eq!(result, Ok("a&a=&b=1".to_owned()));
+}
+
+#[test]
+fn key_only_to_pairs() {
+    let pairs = [("a", None::<u32>)];
+    let mut list = Vec::new();
+    pairs
+        .serialize(Serializer::new(&mut list).none_value(NoneValue::KeyOnly))
+        .unwrap();
+
+    assert_eq!(list, [("a".to_owned(), "".to_owned())]);
+}
+
+#[test]
+fn unsupported_errors() {
+    let unsupported = |kind, location, key: Option<&str>| {
+        Err(Error::Unsupported {
+            kind,
+            location,
+            key: key.map(String::from),
+        })
+    };
+
+    assert_eq!(
+        serde_urlencoded::to_string("top"),
+        unsupported(Kind::Str, Location::TopLevel, None)
+    );
+    assert_eq!(
+        serde_urlencoded::to_string([("a", "b", "c")]),
+        unsupported(Kind::Tuple, Location::Pair, None)
+    );
+    assert_eq!(
+        serde_urlencoded::to_string([((), "b")]),
+        unsupported(Kind::Unit, Location::Key, None)
+    );
+    assert_eq!(
+        serde_urlencoded::to_string([("a", Err::<(), _>(1))]),
+        unsupported(Kind::NewtypeVariant, Location::Value, Some("a"))
+    );
+}
+
+#[test]
+fn unsupported_error_message() {
+    let params = Failing {
+        a: 1,
+        b: vec![2],
+        c: 3,
+    };
+    let error = serde_urlencoded::to_string(&params).unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "unsupported sequence as value for key `b`"
+    );
+    assert_eq!(
+        serde_urlencoded::to_string(1).unwrap_err().to_string(),
+        "unsupported integer at top level"
+    );
+}
+
+#[test]
+fn serialize_max_len() {
+    let params = [("a", "1"), ("b", "é"), ("c", "3")];
+
+    assert_eq!(
+        to_string_with(params, |s| s.max_len(16)),
+        Ok("a=1&b=%C3%A9&c=3".to_owned())
+    );
+
+    let error = to_string_with(params, |s| s.max_len(15)).unwrap_err();
+
+    assert_eq!(
+        error,
+        Error::TooLong {
+            key: "c".to_owned(),
+            max_len: 15,
+        }
+    );
+    assert_eq!(error.to_string(), "output longer than 15 bytes at key `c`");
+}
+
+#[test]
+fn serialize_split_strings() {
+    let params = [("q", "x y"), ("id", "1"), ("id", "22"), ("page", "2")];
+
+    assert_eq!(
+        serde_urlencoded::to_split_strings(params, "id", Encoding::form(), 24),
+        Ok(vec!["q=x+y&id=1&id=22&page=2".to_owned()])
+    );
+    assert_eq!(
+        serde_urlencoded::to_split_strings(params, "id", Encoding::form(), 20),
+        Ok(vec![
+            "q=x+y&id=1&page=2".to_owned(),
+            "q=x+y&id=22&page=2".to_owned(),
+        ])
+    );
+    assert_eq!(
+        serde_urlencoded::to_split_strings(
+            params,
+            "other",
+            Encoding::form(),
+            24
+        ),
+        Ok(vec!["q=x+y&id=1&id=22&page=2".to_owned()])
+    );
+    assert_eq!(
+        serde_urlencoded::to_split_strings(params, "id", Encoding::form(), 17),
+        Err(Error::TooLong {
+            key: "page".to_owned(),
+            max_len: 17,
+        })
+    );
+}
+
+#[test]
+fn serialize_split_strings_encoding() {
+    let params = [("q", "x y"), ("id", "1"), ("id", "2")];
+
+    assert_eq!(
+        serde_urlencoded::to_split_strings(params, "id", Encoding::form(), 15),
+        Ok(vec!["q=x+y&id=1&id=2".to_owned()])
+    );
+    assert_eq!(
+        serde_urlencoded::to_split_strings(params, "id", Encoding::query(), 15),
+        Ok(vec!["q=x%20y&id=1".to_owned(), "q=x%20y&id=2".to_owned()])
+    );
+}
+
+#[test]
+fn serialize_key_prefix() {
+    let mut urlencoder = form_urlencoded::Serializer::new(String::new());
+    [("name", Some("a b")), ("tag", Some("rust")), ("lang", None)]
+        .serialize(Serializer::new(&mut urlencoder).key_prefix("filter_"))
+        .unwrap();
+    [("size", 20), ("number", 3)]
+        .serialize(
+            Serializer::new(&mut urlencoder)
+                .key_prefix(String::from("page."))
+                .pair_order(PairOrder::ByKey),
+        )
+        .unwrap();
+
+    assert_eq!(
+        urlencoder.finish(),
+        "filter_name=a+b&filter_tag=rust&page.number=3&page.size=20"
+    );
+}
+
+#[derive(Serialize)]
+struct Filter<'a> {
+    name: &'a str,
+    tag: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct Page {
+    page: u32,
+    size: u32,
+}
+
+#[test]
+fn query_builder_appends() {
+    let mut builder = QueryBuilder::new();
+    builder
+        .append(Filter {
+            name: "a b",
+            tag: Some("rust"),
+        })
+        .unwrap()
+        .append(Page { page: 1, size: 20 })
+        .unwrap()
+        .pair("tag", "serde")
+        .unwrap();
+
+    assert_eq!(
+        builder.finish(),
+        "name=a+b&tag=rust&page=1&size=20&tag=serde"
+    );
+}
+
+#[test]
+fn query_builder_replaces() {
+    let mut builder = QueryBuilder::new().collisions(Collisions::Replace);
+    builder.append([("id", 1), ("id", 2), ("page", 0)]).unwrap();
+    builder.append(Page { page: 1, size: 20 }).unwrap();
+    builder.pair("id", "3").unwrap();
+
+    assert_eq!(builder.finish(), "page=1&size=20&id=3");
+}
+
+#[test]
+fn query_builder_rejects_collisions() {
+    let mut builder = QueryBuilder::new().collisions(Collisions::Error);
+    builder.append([("id", 1), ("id", 2)]).unwrap();
+    builder.append([("name", "a b"), ("tag", "rust")]).unwrap();
+
+    let error = builder
+        .append(Page { page: 1, size: 20 })
+        .and_then(|builder| builder.pair("tag", "x"));
+    assert_eq!(
+        error.unwrap_err(),
+        Error::KeyCollision {
+            key: "tag".to_owned()
+        }
+    );
+
+    let error = builder.append([("q", 2), ("name", 3)]).unwrap_err();
+    assert_eq!(error.to_string(), "key `name` was already appended");
+    assert_eq!(
+        builder.finish(),
+        "id=1&id=2&name=a+b&tag=rust&page=1&size=20"
+    );
+}
+
+#[test]
+fn query_builder_with_configured_serializer() {
+    let mut builder = QueryBuilder::new().collisions(Collisions::Replace);
+    builder.append(Page { page: 1, size: 20 }).unwrap();
+    [("page", None), ("q", Some("x"))]
+        .serialize(Serializer::new(&mut builder).none_value(NoneValue::KeyOnly))
+        .unwrap();
+    Filter {
+        name: "n",
+        tag: None,
+    }
+    .serialize(Serializer::new(&mut builder).key_prefix("filter_"))
+    .unwrap();
+
+    assert_eq!(builder.finish(), "size=20&page&q=x&filter_name=n");
+}
+
+#[test]
+fn query_builder_into_form_urlencoded() {
+    let mut builder = QueryBuilder::new();
+    builder.pair("b", "2").unwrap();
+
+    let mut urlencoder = form_urlencoded::Serializer::new(String::from("a=1"));
+    builder.finish_into(&mut urlencoder);
+    assert_eq!(urlencoder.finish(), "a=1&b=2");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn query_builder_into_writer() {
+    let mut builder = QueryBuilder::new();
+    builder.append([("name", "a b"), ("tag", "rust")]).unwrap();
+    [("flag", None::<u32>)]
+        .serialize(Serializer::new(&mut builder).none_value(NoneValue::KeyOnly))
+        .unwrap();
+
+    let mut body = Vec::new();
+    builder.finish_writer(&mut body).unwrap();
+    assert_eq!(body, b"name=a+b&tag=rust&flag");
+}
+
+#[test]
+fn serialize_value() {
+    assert_eq!(to_value_string(X::B), Ok("B".to_owned()));
+    assert_eq!(to_value_string(NewType(7)), Ok("7".to_owned()));
+    assert_eq!(to_value_string(-1.5), Ok("-1.5".to_owned()));
+    assert_eq!(to_value_string('é'), Ok("é".to_owned()));
+    assert_eq!(to_value_string(None::<u32>), Ok("".to_owned()));
+}
+
+#[test]
+fn serialize_value_is_not_encoded() {
+    assert_eq!(to_value_string("a b&c"), Ok("a b&c".to_owned()));
+}
+
+#[test]
+fn serialize_value_errors() {
+    assert!(to_value_string([1, 2]).is_err());
+    assert!(to_value_string(f64::NAN).is_ok());
+}
+
+const REWRITE_QUERY: &str =
+    "utm_source=a%20b&q=caf%C3%A9+au+lait&&fbclid=x&tag=a&tag=b&flag";
+
+#[test]
+fn rewrite_nothing() {
+    assert_eq!(Rewriter::new(REWRITE_QUERY).finish(), REWRITE_QUERY);
+    assert_eq!(Rewriter::new("").finish(), "");
+    assert_eq!(Rewriter::new("&").remove("a").finish(), "&");
+}
+
+#[test]
+fn rewrite_filter() {
+    let query = Rewriter::new(REWRITE_QUERY)
+        .filter(|key, value| !key.starts_with("utm_") && value != "x")
+        .finish();
+
+    assert_eq!(query, "q=caf%C3%A9+au+lait&&tag=a&tag=b&flag");
+
+    let query = Rewriter::new(REWRITE_QUERY)
+        .filter(|_, value| value != "café au lait")
+        .finish();
+
+    assert_eq!(query, "utm_source=a%20b&&fbclid=x&tag=a&tag=b&flag");
+}
+
+#[test]
+fn rewrite_remove_and_rename() {
+    let query = Rewriter::new("%71=1&q=2&tag=3&flag")
+        .remove("tag")
+        .rename("q", "search term")
+        .rename("flag", "f")
+        .finish();
+
+    assert_eq!(query, "search+term=1&search+term=2&f");
+}
+
+#[test]
+fn rewrite_set() {
+    let query = Rewriter::new(REWRITE_QUERY)
+        .set("tag", "c&d")
+        .unwrap()
+        .set("sort", X::B)
+        .unwrap()
+        .set("utm_source", None::<&str>)
+        .unwrap()
+        .set("flag", Some(true))
+        .unwrap()
+        .finish();
+
+    assert_eq!(
+        query,
+        "q=caf%C3%A9+au+lait&&fbclid=x&tag=c%26d&flag=true&sort=B"
+    );
+}
+
+#[test]
+fn rewrite_replace() {
+    let query = Rewriter::new("a=1&b=2&a=3")
+        .replace("a", 4.5)
+        .unwrap()
+        .replace("c", 'é')
+        .unwrap()
+        .finish();
+
+    assert_eq!(query, "a=4.5&b=2");
+}
+
+#[test]
+fn rewrite_unsupported_value() {
+    let error = Rewriter::new("a=1").set("a", [1, 2]).unwrap_err();
+
+    assert_eq!(
+        error,
+        Error::Unsupported {
+            kind: Kind::Tuple,
+            location: Location::Value,
+            key: Some("a".to_owned()),
+        }
+    );
+}
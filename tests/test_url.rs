@@ -0,0 +1,89 @@
+#![cfg(feature = "url")]
+
+use serde::de::Deserialize;
+use serde_derive::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+struct Search {
+    q: String,
+    page: u32,
+}
+
+#[test]
+fn deserialize_url() {
+    let url = Url::parse("https://example.com/search?q=caf%C3%A9&page=3#top")
+        .unwrap();
+    let search = Search {
+        q: "café".to_owned(),
+        page: 3,
+    };
+
+    assert_eq!(serde_urlencoded::from_url(&url), Ok(search));
+}
+
+#[test]
+fn deserialize_url_without_query() {
+    let url = Url::parse("https://example.com/search").unwrap();
+
+    assert_eq!(serde_urlencoded::from_url(&url), Ok(()));
+}
+
+#[test]
+fn append_to_url_keeps_existing_pairs() {
+    let mut url = Url::parse("https://example.com/search?lang=fr#top").unwrap();
+    let search = Search {
+        q: "a b".to_owned(),
+        page: 1,
+    };
+    serde_urlencoded::append_to_url(&mut url, &search).unwrap();
+
+    assert_eq!(
+        url.as_str(),
+        "https://example.com/search?lang=fr&q=a+b&page=1#top"
+    );
+}
+
+#[test]
+fn append_nothing_to_url() {
+    let mut url = Url::parse("https://example.com/search").unwrap();
+    serde_urlencoded::append_to_url(&mut url, ()).unwrap();
+
+    assert_eq!(url.as_str(), "https://example.com/search");
+}
+
+#[test]
+fn append_to_url_error_leaves_url_untouched() {
+    let mut url = Url::parse("https://example.com/?lang=fr").unwrap();
+    let pairs = &[("q", Ok("rust")), ("page", Err(2))];
+
+    assert!(serde_urlencoded::append_to_url(&mut url, pairs).is_err());
+    assert_eq!(url.as_str(), "https://example.com/?lang=fr");
+}
+
+#[test]
+fn deserialize_query_pairs() {
+    let url = Url::parse("https://example.com/?q=a+b&page=2").unwrap();
+    let deserializer =
+        serde_urlencoded::Deserializer::from_pairs(url.query_pairs());
+
+    assert_eq!(
+        Search::deserialize(deserializer),
+        Ok(Search {
+            q: "a b".to_owned(),
+            page: 2,
+        })
+    );
+}
+
+#[test]
+fn query_builder_into_url() {
+    let mut url = Url::parse("https://example.com/?old=1").unwrap();
+    serde_urlencoded::QueryBuilder::new().finish_url(&mut url);
+    assert_eq!(url.as_str(), "https://example.com/");
+
+    let mut builder = serde_urlencoded::QueryBuilder::new();
+    builder.append([("page", 1), ("size", 20)]).unwrap();
+    builder.finish_url(&mut url);
+    assert_eq!(url.as_str(), "https://example.com/?page=1&size=20");
+}